@@ -0,0 +1,214 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio, Child, ChildStdin, ChildStdout, ChildStderr, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::policy::{
+    exit_limit, is_memory_error, prepare_command, wait_exited, ResourceLimit, RunPolicy, Violation, Watchdog,
+};
+
+// How often the watchdog checks the policy
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(50);
+
+// How a run ended
+#[derive(Debug)]
+pub enum RunStatus {
+    Completed(ExitStatus),
+    TimedOut,
+    LimitExceeded(ResourceLimit),
+}
+
+// Child process control structure
+pub struct ChildController {
+    child_id: u32,
+    stdin: Option<ChildStdin>,
+    stdout: Option<ChildStdout>,
+    stderr: Option<ChildStderr>,
+    child: Option<Child>, // Option to own the Child process handle
+    policy: RunPolicy,
+    output_bytes: Arc<AtomicU64>, // Bytes read from stdout and stderr so far
+    memory_error: bool,    // Whether stderr reported a failed allocation
+    finished: Arc<AtomicBool>,         // Set once the child has been waited for
+    watchdog: Arc<Mutex<Watchdog>>,    // Interrupts, then kills, the child when it breaks the policy
+}
+
+impl ChildController {
+    // Start a child process with piped stdio under `policy` and return a controller
+    pub fn new(command: &str, args: &[&str], id: u32, policy: RunPolicy) -> io::Result<Self> {
+        let mut command_builder = Command::new(command);
+        command_builder
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        Self::from_command(command_builder, id, policy)
+    }
+
+    // Start an already configured command (arguments, stdio, working directory) under `policy`
+    pub fn from_command(command_builder: Command, id: u32, policy: RunPolicy) -> io::Result<Self> {
+        Self::with_logs(command_builder, id, policy, Vec::new())
+    }
+
+    // Like `from_command`, for a command whose output goes to the files `logs`; what is
+    // written to them counts towards the policy's output limit
+    pub fn with_logs(mut command_builder: Command, id: u32, policy: RunPolicy, logs: Vec<PathBuf>) -> io::Result<Self> {
+        prepare_command(&mut command_builder, &policy); // Own process group and rlimits
+        let mut child_process_handle = command_builder.spawn()?; // Spawn the child process
+
+        let output_bytes = Arc::new(AtomicU64::new(0));
+        let finished = Arc::new(AtomicBool::new(false));
+        let watchdog = Arc::new(Mutex::new(Watchdog::new(child_process_handle.id(), policy)));
+        if watchdog.lock().unwrap().is_needed() {
+            Self::spawn_watchdog(Arc::clone(&watchdog), Arc::clone(&output_bytes), logs, Arc::clone(&finished));
+        }
+
+        Ok(Self {
+            child_id: id,
+            stdin: child_process_handle.stdin.take(),
+            stdout: child_process_handle.stdout.take(),
+            stderr: child_process_handle.stderr.take(),
+            child: Some(child_process_handle),
+            policy,
+            output_bytes,
+            memory_error: false,
+            finished,
+            watchdog,
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child_id
+    }
+
+    // Operating system pid of the child, which is also its process group id
+    pub fn pid(&self) -> Option<u32> {
+        self.child.as_ref().map(|child_handle| child_handle.id())
+    }
+
+    // Check the policy until the child has exited; `finished` is set under the watchdog's lock
+    fn spawn_watchdog(
+        watchdog: Arc<Mutex<Watchdog>>,
+        output_bytes: Arc<AtomicU64>,
+        logs: Vec<PathBuf>,
+        finished: Arc<AtomicBool>,
+    ) {
+        thread::spawn(move || loop {
+            let logged: u64 = logs.iter().filter_map(|path| fs::metadata(path).ok()).map(|m| m.len()).sum();
+            {
+                let mut watchdog = watchdog.lock().unwrap();
+                if finished.load(Ordering::Relaxed) {
+                    return;
+                }
+                watchdog.check(output_bytes.load(Ordering::Relaxed) + logged);
+            }
+            thread::sleep(WATCHDOG_INTERVAL);
+        });
+    }
+
+    // Output budget left under the policy, if any
+    fn remaining_output(&self) -> Option<u64> {
+        let read = self.output_bytes.load(Ordering::Relaxed);
+        self.policy.max_output_bytes.map(|max| max.saturating_sub(read))
+    }
+
+    // Account for `read` bytes of output; the watchdog stops the child once it goes over its budget
+    fn record_output(&mut self, read: usize) {
+        self.output_bytes.fetch_add(read as u64, Ordering::Relaxed);
+    }
+
+    // Send a command string to the child process's stdin
+    pub fn send_command(&mut self, command_text: &str) -> io::Result<()> {
+        if let Some(ref mut stdin_pipe) = self.stdin {
+            stdin_pipe.write_all(command_text.as_bytes())?;
+            stdin_pipe.write_all(b"\n")?; // Append newline, common for command-line tools
+            stdin_pipe.flush()?; // Ensure the command is sent immediately
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Stdin is not available for this child process.",
+            ));
+        }
+        Ok(())
+    }
+
+    // Read output from the child process's stdout
+    // Note: read_to_string blocks until EOF. For continuous output, line-by-line reading might be better.
+    pub fn read_output(&mut self) -> io::Result<String> {
+        let mut output_buffer = String::new();
+        let remaining = self.remaining_output();
+        if let Some(ref mut stdout_pipe) = self.stdout {
+            // This will block until the stdout pipe is closed by the child process (e.g., on exit)
+            // or until an error occurs. With an output limit, stop one byte past the budget.
+            let read = match remaining {
+                Some(remaining) => stdout_pipe.take(remaining + 1).read_to_string(&mut output_buffer)?,
+                None => stdout_pipe.read_to_string(&mut output_buffer)?,
+            };
+            self.record_output(read);
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Stdout is not available for this child process.",
+            ));
+        }
+        Ok(output_buffer)
+    }
+    
+    // Read error output from the child process's stderr
+    pub fn read_error_output(&mut self) -> io::Result<String> {
+        let mut error_buffer = String::new();
+        let remaining = self.remaining_output();
+        if let Some(ref mut stderr_pipe) = self.stderr {
+            // Non-blocking read attempt for stderr, as it might not always have data
+            // For simplicity, we'll use read_to_string which can block if stderr isn't closed.
+            // A more robust solution might use non-blocking reads or select.
+            let read = match remaining {
+                Some(remaining) => stderr_pipe.take(remaining + 1).read_to_string(&mut error_buffer)?,
+                None => stderr_pipe.read_to_string(&mut error_buffer)?,
+            };
+            self.record_output(read);
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Stderr is not available for this child process.",
+            ));
+        }
+        if is_memory_error(&error_buffer) {
+            self.memory_error = true;
+        }
+        Ok(error_buffer)
+    }
+
+
+    // Wait for the child process to exit
+    pub fn wait(&mut self) -> io::Result<RunStatus> {
+        if let Some(ref mut child_handle) = self.child {
+            // Stop the watchdog before the child is reaped and its process group id freed.
+            // If the child was already reaped there is nothing left to protect.
+            let _ = wait_exited(child_handle.id());
+            {
+                let _watchdog = self.watchdog.lock().unwrap();
+                self.finished.store(true, Ordering::Relaxed);
+            }
+            let status = child_handle.wait()?;
+            // A limit enforced by the watchdog takes precedence over how the process happened to end
+            match self.watchdog.lock().unwrap().violation() {
+                Some(Violation::TimedOut) => return Ok(RunStatus::TimedOut),
+                Some(Violation::LimitExceeded(limit)) => return Ok(RunStatus::LimitExceeded(limit)),
+                None => {}
+            }
+            if let Some(limit) = exit_limit(&self.policy, &status, || self.memory_error) {
+                return Ok(RunStatus::LimitExceeded(limit));
+            }
+            Ok(RunStatus::Completed(status))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound, // Changed from Other for clarity
+                "Child process handle does not exist or was already taken.",
+            ))
+        }
+    }
+}
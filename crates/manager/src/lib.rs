@@ -0,0 +1,9 @@
+pub mod child;
+pub mod policy;
+pub mod scheduler;
+pub mod sweep;
+
+pub use child::{ChildController, RunStatus};
+pub use policy::{GroupSignal, ResourceLimit, RunPolicy, Violation, Watchdog};
+pub use scheduler::{JobId, JobInfo, JobListener, JobSpec, JobState, Scheduler};
+pub use sweep::{Axis, AxisValues, Sampling, SweepCounts, SweepDefinition, SweepId, SweepPoint, SweepStatus, Sweeps};
@@ -0,0 +1,339 @@
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+// How long a run that timed out gets to exit after the interrupt before its process group is killed
+pub const LIMIT_GRACE: Duration = Duration::from_secs(5);
+
+// Limits for one run; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RunPolicy {
+    pub timeout_ms: Option<u64>,        // Wall-clock time, enforced by the watchdog
+    pub cpu_seconds: Option<u64>,       // RLIMIT_CPU, the kernel sends SIGXCPU when it is reached
+    pub memory_bytes: Option<u64>,      // RLIMIT_AS, allocations beyond it fail
+    pub max_output_bytes: Option<u64>,  // stdout and stderr combined, enforced by the watchdog
+}
+
+// Which limit of the policy a run ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceLimit {
+    Cpu,
+    Memory,
+    Output,
+}
+
+// Why the watchdog stopped a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    TimedOut,
+    LimitExceeded(ResourceLimit),
+}
+
+// Signals sent to a run's process group. Without Unix signals each of them ends the process tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupSignal {
+    Interrupt,
+    Terminate,
+    Kill,
+}
+
+// Send `signal` to the process group led by `pid`. Only call this while the leader has not
+// been reaped; afterwards the id may belong to an unrelated process.
+#[cfg(unix)]
+pub fn signal_group(pid: u32, signal: GroupSignal) -> bool {
+    let signal = match signal {
+        GroupSignal::Interrupt => libc::SIGINT,
+        GroupSignal::Terminate => libc::SIGTERM,
+        GroupSignal::Kill => libc::SIGKILL,
+    };
+    unsafe { libc::kill(-(pid as libc::pid_t), signal) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn signal_group(pid: u32, _signal: GroupSignal) -> bool {
+    use std::process::Stdio;
+
+    Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// Start `command` in its own process group, so signals reach everything it spawns, with the
+// policy's CPU and memory rlimits, which its descendants inherit. Only the watchdog's limits
+// apply where there are no rlimits.
+#[cfg(unix)]
+pub fn prepare_command(command: &mut Command, policy: &RunPolicy) {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+
+    let (cpu, memory) = (policy.cpu_seconds, policy.memory_bytes);
+    if cpu.is_none() && memory.is_none() {
+        return;
+    }
+    // Runs in the forked child before exec; setrlimit is async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(seconds) = cpu {
+                let limit = libc::rlimit {
+                    rlim_cur: seconds as libc::rlim_t,
+                    rlim_max: seconds.saturating_add(1) as libc::rlim_t, // Hard SIGKILL one second later
+                };
+                if libc::setrlimit(libc::RLIMIT_CPU, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(bytes) = memory {
+                let limit = libc::rlimit {
+                    rlim_cur: bytes as libc::rlim_t,
+                    rlim_max: bytes as libc::rlim_t,
+                };
+                if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn prepare_command(_command: &mut Command, _policy: &RunPolicy) {}
+
+// Block until the process `pid` has exited without reaping it, so its id cannot be reused
+// until `Child::wait` collects it. Whoever may still signal the group stops before that.
+#[cfg(unix)]
+pub fn wait_exited(pid: u32) -> io::Result<()> {
+    loop {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        let flags = libc::WEXITED | libc::WNOWAIT;
+        if unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, flags) } == 0 {
+            return Ok(());
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn wait_exited(_pid: u32) -> io::Result<()> {
+    Ok(())
+}
+
+// Whether the process `pid` has exited, without blocking and without reaping it, so its
+// group can still be signalled safely before `Child::wait` collects it
+#[cfg(unix)]
+pub fn has_exited(pid: u32) -> io::Result<bool> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let flags = libc::WEXITED | libc::WNOWAIT | libc::WNOHANG;
+    if unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, flags) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // With WNOHANG and nothing to report, the info is left zeroed
+    Ok(unsafe { info.si_pid() } != 0)
+}
+
+// Signal that ended the process, if any
+#[cfg(unix)]
+pub fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+pub fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+// Whether stderr reports an allocation that failed, as under RLIMIT_AS
+pub fn is_memory_error(stderr: &str) -> bool {
+    stderr.contains("MemoryError") || stderr.contains("Cannot allocate memory")
+}
+
+// Which rlimit a run that ended on its own ran into: RLIMIT_CPU ends it with SIGXCPU, while
+// RLIMIT_AS only shows as a failure with `memory_error` (usually a look at stderr) true
+pub fn exit_limit(
+    policy: &RunPolicy,
+    status: &ExitStatus,
+    memory_error: impl FnOnce() -> bool,
+) -> Option<ResourceLimit> {
+    #[cfg(unix)]
+    if policy.cpu_seconds.is_some() && exit_signal(status) == Some(libc::SIGXCPU) {
+        return Some(ResourceLimit::Cpu);
+    }
+    if policy.memory_bytes.is_some() && !status.success() && memory_error() {
+        return Some(ResourceLimit::Memory);
+    }
+    None
+}
+
+// Enforces the wall-clock timeout and the output budget of one run. A timeout interrupts the
+// process group, which is killed if it is still running `LIMIT_GRACE` later, so mas can still
+// write out its trajectory; a run over its output budget is killed straight away.
+//
+// `check` must only be called while the run's leader has not been reaped.
+#[derive(Debug)]
+pub struct Watchdog {
+    pid: u32,
+    policy: RunPolicy,
+    started: Instant,
+    violation: Option<Violation>,
+    kill_at: Option<Instant>,
+}
+
+impl Watchdog {
+    pub fn new(pid: u32, policy: RunPolicy) -> Self {
+        Self {
+            pid,
+            policy,
+            started: Instant::now(),
+            violation: None,
+            kill_at: None,
+        }
+    }
+
+    pub fn policy(&self) -> &RunPolicy {
+        &self.policy
+    }
+
+    pub fn violation(&self) -> Option<Violation> {
+        self.violation
+    }
+
+    // Whether the policy has anything for `check` to enforce
+    pub fn is_needed(&self) -> bool {
+        self.policy.timeout_ms.is_some() || self.policy.max_output_bytes.is_some()
+    }
+
+    // Compare the run against its policy, given the output read so far, and interrupt or kill
+    // it as needed. Returns the violation once there is one.
+    pub fn check(&mut self, output_bytes: u64) -> Option<Violation> {
+        if let Some(at) = self.kill_at {
+            if Instant::now() >= at {
+                signal_group(self.pid, GroupSignal::Kill);
+                self.kill_at = None;
+            }
+            return self.violation;
+        }
+        if self.violation.is_some() {
+            return self.violation;
+        }
+
+        let timed_out = self
+            .policy
+            .timeout_ms
+            .is_some_and(|ms| self.started.elapsed() >= Duration::from_millis(ms));
+        let output_exceeded = self.policy.max_output_bytes.is_some_and(|max| output_bytes > max);
+        if timed_out {
+            self.violation = Some(Violation::TimedOut);
+            signal_group(self.pid, GroupSignal::Interrupt);
+            self.kill_at = Some(Instant::now() + LIMIT_GRACE);
+        } else if output_exceeded {
+            self.violation = Some(Violation::LimitExceeded(ResourceLimit::Output));
+            signal_group(self.pid, GroupSignal::Kill);
+        }
+        self.violation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_errors_in_stderr() {
+        assert!(is_memory_error("Traceback (most recent call last):\nMemoryError\n"));
+        assert!(is_memory_error("mmap failed: Cannot allocate memory"));
+        assert!(!is_memory_error("ValueError: bad profile"));
+    }
+
+    #[test]
+    fn watchdog_is_only_needed_for_a_timeout_or_an_output_limit() {
+        let policy = RunPolicy {
+            cpu_seconds: Some(1),
+            memory_bytes: Some(1 << 30),
+            ..RunPolicy::default()
+        };
+        assert!(!Watchdog::new(1, policy).is_needed());
+        assert!(Watchdog::new(1, RunPolicy { timeout_ms: Some(1), ..policy }).is_needed());
+        assert!(Watchdog::new(1, RunPolicy { max_output_bytes: Some(1), ..policy }).is_needed());
+    }
+
+    #[cfg(unix)]
+    mod unix {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{Child, Command};
+
+        use super::*;
+
+        fn spawn(args: &[&str], policy: &RunPolicy) -> Child {
+            let mut command = Command::new("sleep");
+            command.args(args);
+            prepare_command(&mut command, policy);
+            command.spawn().unwrap()
+        }
+
+        #[test]
+        fn limits_a_run_ended_on() {
+            let cpu = RunPolicy {
+                cpu_seconds: Some(1),
+                ..RunPolicy::default()
+            };
+            let memory = RunPolicy {
+                memory_bytes: Some(1 << 30),
+                ..RunPolicy::default()
+            };
+            let xcpu = ExitStatus::from_raw(libc::SIGXCPU);
+            let failed = ExitStatus::from_raw(1 << 8);
+            let succeeded = ExitStatus::from_raw(0);
+
+            assert_eq!(exit_limit(&cpu, &xcpu, || false), Some(ResourceLimit::Cpu));
+            assert_eq!(exit_limit(&RunPolicy::default(), &xcpu, || false), None);
+            assert_eq!(exit_limit(&memory, &failed, || true), Some(ResourceLimit::Memory));
+            assert_eq!(exit_limit(&memory, &failed, || false), None);
+            assert_eq!(exit_limit(&memory, &succeeded, || true), None);
+            assert_eq!(exit_limit(&cpu, &failed, || true), None);
+        }
+
+        #[test]
+        fn exit_is_seen_without_reaping() {
+            let mut child = spawn(&["0.2"], &RunPolicy::default());
+            assert!(!has_exited(child.id()).unwrap());
+            wait_exited(child.id()).unwrap();
+            assert!(has_exited(child.id()).unwrap());
+            // Still there to be collected
+            assert!(child.try_wait().unwrap().unwrap().success());
+            assert!(has_exited(child.id()).is_err());
+        }
+
+        #[test]
+        fn watchdog_interrupts_on_timeout_and_kills_on_output() {
+            let policy = RunPolicy {
+                timeout_ms: Some(0),
+                ..RunPolicy::default()
+            };
+            let mut child = spawn(&["30"], &policy);
+            let mut watchdog = Watchdog::new(child.id(), policy);
+            assert_eq!(watchdog.check(0), Some(Violation::TimedOut));
+            assert_eq!(exit_signal(&child.wait().unwrap()), Some(libc::SIGINT));
+
+            let policy = RunPolicy {
+                max_output_bytes: Some(10),
+                ..RunPolicy::default()
+            };
+            let mut child = spawn(&["30"], &policy);
+            let mut watchdog = Watchdog::new(child.id(), policy);
+            assert_eq!(watchdog.check(10), None);
+            assert_eq!(watchdog.check(11), Some(Violation::LimitExceeded(ResourceLimit::Output)));
+            assert_eq!(exit_signal(&child.wait().unwrap()), Some(libc::SIGKILL));
+        }
+    }
+}
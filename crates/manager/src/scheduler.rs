@@ -0,0 +1,615 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use threadpool::ThreadPool;
+
+use crate::child::{ChildController, RunStatus};
+use crate::policy::{
+    exit_signal, is_memory_error, signal_group, wait_exited, GroupSignal, ResourceLimit, RunPolicy,
+};
+
+// How long a cancelled job gets to exit after SIGINT before its process group is killed
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+// How much of the end of a failed job's stderr.log is searched for a memory error
+const STDERR_TAIL_BYTES: u64 = 64 * 1024;
+
+pub type JobId = u64;
+
+// Called with the job's new state every time a job changes state
+pub type JobListener = Arc<dyn Fn(&JobInfo) + Send + Sync>;
+
+// What to run; `profile` is only a label, the command is `program args...`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSpec {
+    pub profile: String,
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    // Working directory of the run; stdout.log and stderr.log are written here.
+    // Without one, output is discarded.
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    // Higher runs first, equal priorities run in submission order
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub policy: RunPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobState::Succeeded | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+// Snapshot of a job, as returned by the query API and passed to the listener
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub spec: JobSpec,
+    pub state: JobState,
+    // Unix timestamps in milliseconds
+    pub submitted_ms: u64,
+    pub started_ms: Option<u64>,
+    pub finished_ms: Option<u64>,
+    pub pid: Option<u32>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    pub limit: Option<ResourceLimit>,
+    // Set when the job could not be started
+    pub error: Option<String>,
+    pub stdout_path: Option<PathBuf>,
+    pub stderr_path: Option<PathBuf>,
+}
+
+// Heap entry: the greatest entry is the highest priority, then the earliest submission
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct QueueEntry {
+    priority: i32,
+    order: Reverse<JobId>,
+}
+
+struct Job {
+    info: JobInfo,
+    cancel_requested: bool,
+    // The process has exited and is about to be reaped, after which its process group id may
+    // be reused; nothing may signal the group from then on. Only read or set under the state lock.
+    exited: bool,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: BTreeMap<JobId, Job>,
+    queue: BinaryHeap<QueueEntry>,
+    next_id: JobId,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    changed: Condvar, // Notified on every state change, used by wait_idle
+    listener: Mutex<Option<JobListener>>,
+}
+
+// Runs submitted jobs at most `max_concurrency` at a time, by priority and then in FIFO order.
+//
+// Every submission queues one task on the thread pool; a task picks the best queued job
+// when it starts rather than the job it was submitted for, so the pool size is the
+// concurrency limit and the heap decides the order.
+pub struct Scheduler {
+    inner: Arc<Inner>,
+    pool: Mutex<ThreadPool>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+impl Inner {
+    // Apply `update` to a job, then notify the listener and waiters
+    fn update(&self, id: JobId, update: impl FnOnce(&mut Job)) {
+        let info = {
+            let mut state = self.state.lock().unwrap();
+            let Some(job) = state.jobs.get_mut(&id) else {
+                return;
+            };
+            update(job);
+            job.info.clone()
+        };
+        self.notify(&info);
+        self.changed.notify_all();
+    }
+
+    fn notify(&self, info: &JobInfo) {
+        let listener = self.listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            listener(info);
+        }
+    }
+
+    // Take the best queued job and mark it running; cancelled entries are skipped.
+    // The listener hears about it once the process has started and has a pid.
+    fn next_job(&self) -> Option<JobInfo> {
+        let info = {
+            let mut state = self.state.lock().unwrap();
+            loop {
+                let entry = state.queue.pop()?;
+                let job = state.jobs.get_mut(&entry.order.0)?;
+                if job.info.state == JobState::Queued {
+                    job.info.state = JobState::Running;
+                    job.info.started_ms = Some(now_ms());
+                    break job.info.clone();
+                }
+            }
+        };
+        self.changed.notify_all();
+        Some(info)
+    }
+
+    fn run_next(&self) {
+        let Some(info) = self.next_job() else {
+            return;
+        };
+        let id = info.id;
+
+        let mut controller = match spawn(&info) {
+            Ok(controller) => controller,
+            Err(e) => {
+                self.update(id, |job| {
+                    job.info.state = JobState::Failed;
+                    job.info.finished_ms = Some(now_ms());
+                    job.info.error = Some(e.to_string());
+                });
+                return;
+            }
+        };
+
+        // A cancel that arrived while the process was being spawned had no pid to signal
+        let pid = controller.pid();
+        let mut cancelled_early = false;
+        self.update(id, |job| {
+            job.info.pid = pid;
+            cancelled_early = job.cancel_requested;
+        });
+        if cancelled_early && let Some(pid) = pid {
+            signal_group(pid, GroupSignal::Kill);
+        }
+
+        // Cancel and shutdown signal the group under the state lock and skip exited jobs, so
+        // marking the job before reaping keeps them off a process group id that may be reused
+        if let Some(pid) = pid {
+            let _ = wait_exited(pid);
+        }
+        if let Some(job) = self.state.lock().unwrap().jobs.get_mut(&id) {
+            job.exited = true;
+        }
+        let status = controller.wait();
+
+        let failed = matches!(&status, Ok(RunStatus::Completed(status)) if !status.success());
+        let memory_error = failed
+            && info.spec.policy.memory_bytes.is_some()
+            && info.stderr_path.as_ref().is_some_and(|path| {
+                read_tail(path, STDERR_TAIL_BYTES).is_ok_and(|text| is_memory_error(&text))
+            });
+        self.update(id, |job| {
+            job.info.finished_ms = Some(now_ms());
+            job.info.state = match status {
+                _ if job.cancel_requested => JobState::Cancelled,
+                Ok(RunStatus::Completed(status)) => {
+                    job.info.exit_code = status.code();
+                    job.info.signal = exit_signal(&status);
+                    if status.success() {
+                        JobState::Succeeded
+                    } else {
+                        if memory_error {
+                            job.info.limit = Some(ResourceLimit::Memory);
+                        }
+                        JobState::Failed
+                    }
+                }
+                Ok(RunStatus::TimedOut) => {
+                    job.info.timed_out = true;
+                    JobState::Failed
+                }
+                Ok(RunStatus::LimitExceeded(limit)) => {
+                    job.info.limit = Some(limit);
+                    JobState::Failed
+                }
+                Err(e) => {
+                    job.info.error = Some(e.to_string());
+                    JobState::Failed
+                }
+            };
+        });
+    }
+}
+
+// The last `max` bytes of a file, with invalid UTF-8 replaced
+fn read_tail(path: &Path, max: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max)))?;
+    let mut bytes = Vec::new();
+    file.take(max).read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// Start the job's process, with output going to log files in its working directory
+fn spawn(info: &JobInfo) -> io::Result<ChildController> {
+    let spec = &info.spec;
+    let mut command = Command::new(&spec.program);
+    command.args(&spec.args).stdin(Stdio::null());
+    if let Some(dir) = &spec.working_dir {
+        fs::create_dir_all(dir)?;
+        command.current_dir(dir);
+    }
+    match (&info.stdout_path, &info.stderr_path) {
+        (Some(stdout), Some(stderr)) => {
+            command.stdout(File::create(stdout)?).stderr(File::create(stderr)?);
+            ChildController::with_logs(command, info.id as u32, spec.policy, vec![stdout.clone(), stderr.clone()])
+        }
+        _ => {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+            ChildController::from_command(command, info.id as u32, spec.policy)
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State::default()),
+                changed: Condvar::new(),
+                listener: Mutex::new(None),
+            }),
+            pool: Mutex::new(ThreadPool::new(max_concurrency.max(1))),
+        }
+    }
+
+    pub fn set_listener(&self, listener: JobListener) {
+        *self.inner.listener.lock().unwrap() = Some(listener);
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.pool.lock().unwrap().max_count()
+    }
+
+    // Lowering the limit lets running jobs finish; fewer are started afterwards
+    pub fn set_max_concurrency(&self, max_concurrency: usize) {
+        self.pool.lock().unwrap().set_num_threads(max_concurrency.max(1));
+    }
+
+    // Queue a job and return its id
+    pub fn submit(&self, spec: JobSpec) -> JobId {
+        let info = {
+            let mut state = self.inner.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            let log = |name: &str| spec.working_dir.as_ref().map(|dir| dir.join(name));
+            let info = JobInfo {
+                id,
+                state: JobState::Queued,
+                submitted_ms: now_ms(),
+                started_ms: None,
+                finished_ms: None,
+                pid: None,
+                exit_code: None,
+                signal: None,
+                timed_out: false,
+                limit: None,
+                error: None,
+                stdout_path: log("stdout.log"),
+                stderr_path: log("stderr.log"),
+                spec,
+            };
+            state.queue.push(QueueEntry {
+                priority: info.spec.priority,
+                order: Reverse(id),
+            });
+            state.jobs.insert(
+                id,
+                Job {
+                    info: info.clone(),
+                    cancel_requested: false,
+                    exited: false,
+                },
+            );
+            info
+        };
+        self.inner.notify(&info);
+        self.inner.changed.notify_all();
+
+        let inner = Arc::clone(&self.inner);
+        self.pool.lock().unwrap().execute(move || inner.run_next());
+        info.id
+    }
+
+    // Cancel a queued or running job. Running jobs get SIGINT and are killed after a grace
+    // period. Returns false if the job does not exist or has already finished.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let (info, signalled) = {
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(job) = state.jobs.get_mut(&id) else {
+                return false;
+            };
+            match job.info.state {
+                JobState::Queued => {
+                    job.info.state = JobState::Cancelled;
+                    job.info.finished_ms = Some(now_ms());
+                    (Some(job.info.clone()), None)
+                }
+                JobState::Running => {
+                    job.cancel_requested = true;
+                    // Signalled under the lock, before the job can be marked exited and reaped
+                    let pid = job.info.pid.filter(|_| !job.exited);
+                    if let Some(pid) = pid {
+                        signal_group(pid, GroupSignal::Interrupt);
+                    }
+                    (None, pid)
+                }
+                _ => return false,
+            }
+        };
+
+        if let Some(info) = info {
+            self.inner.notify(&info);
+            self.inner.changed.notify_all();
+        }
+        if let Some(pid) = signalled {
+            let inner = Arc::clone(&self.inner);
+            thread::spawn(move || {
+                thread::sleep(CANCEL_GRACE);
+                let state = inner.state.lock().unwrap();
+                let still_running = state.jobs.get(&id).is_some_and(|job| {
+                    job.info.state == JobState::Running && job.info.pid == Some(pid) && !job.exited
+                });
+                if still_running {
+                    signal_group(pid, GroupSignal::Kill);
+                }
+            });
+        }
+        true
+    }
+
+    // Cancel every job that has not finished yet
+    pub fn cancel_all(&self) {
+        let ids: Vec<JobId> = {
+            let state = self.inner.state.lock().unwrap();
+            state
+                .jobs
+                .values()
+                .filter(|job| !job.info.state.is_finished())
+                .map(|job| job.info.id)
+                .collect()
+        };
+        for id in ids {
+            self.cancel(id);
+        }
+    }
+
+    // Cancel every job that has not finished yet and wait for the running ones to exit,
+    // killing those still running after the grace period. Unlike `cancel_all` this does not
+    // rely on a background thread, so it is safe to call while the process is exiting.
+    pub fn shutdown(&self) {
+        self.cancel_all();
+
+        let deadline = Instant::now() + CANCEL_GRACE;
+        let mut state = self.inner.state.lock().unwrap();
+        loop {
+            let running: Vec<u32> = state
+                .jobs
+                .values()
+                .filter(|job| job.info.state == JobState::Running && !job.exited)
+                .filter_map(|job| job.info.pid)
+                .collect();
+            if running.is_empty() {
+                break;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                for pid in running {
+                    signal_group(pid, GroupSignal::Kill);
+                }
+                break;
+            }
+            state = self.inner.changed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        drop(state);
+        self.wait_idle();
+    }
+
+    pub fn get(&self, id: JobId) -> Option<JobInfo> {
+        let state = self.inner.state.lock().unwrap();
+        state.jobs.get(&id).map(|job| job.info.clone())
+    }
+
+    // All jobs in submission order
+    pub fn list(&self) -> Vec<JobInfo> {
+        let state = self.inner.state.lock().unwrap();
+        state.jobs.values().map(|job| job.info.clone()).collect()
+    }
+
+    // Queued jobs in the order they will be started
+    pub fn queued(&self) -> Vec<JobId> {
+        let state = self.inner.state.lock().unwrap();
+        let mut entries: Vec<&QueueEntry> = state
+            .queue
+            .iter()
+            .filter(|entry| state.jobs.get(&entry.order.0).is_some_and(|job| job.info.state == JobState::Queued))
+            .collect();
+        entries.sort_by(|a, b| b.cmp(a));
+        entries.into_iter().map(|entry| entry.order.0).collect()
+    }
+
+    // Block until no job is queued or running
+    pub fn wait_idle(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        while state.jobs.values().any(|job| !job.info.state.is_finished()) {
+            state = self.inner.changed.wait(state).unwrap();
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn spec(script: &str, priority: i32) -> JobSpec {
+        JobSpec {
+            profile: String::new(),
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            working_dir: None,
+            priority,
+            policy: RunPolicy::default(),
+        }
+    }
+
+    // Not through sh, which catches SIGINT while it starts up under -c
+    fn sleep(seconds: &str) -> JobSpec {
+        JobSpec {
+            program: "sleep".into(),
+            args: vec![seconds.into()],
+            ..spec("", 0)
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mas-scheduler-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // Block until the job has a running process
+    fn wait_started(scheduler: &Scheduler, id: JobId) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while scheduler.get(id).unwrap().pid.is_none() {
+            assert!(Instant::now() < deadline, "job {id} did not start");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn runs_by_priority_then_in_submission_order() {
+        let scheduler = Scheduler::new(1);
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let record = Arc::clone(&started);
+        scheduler.set_listener(Arc::new(move |info: &JobInfo| {
+            if info.state == JobState::Running && info.pid.is_some() {
+                record.lock().unwrap().push(info.id);
+            }
+        }));
+
+        let blocker = scheduler.submit(sleep("0.3"));
+        wait_started(&scheduler, blocker);
+        let low = scheduler.submit(spec("true", 0));
+        let high = scheduler.submit(spec("true", 5));
+        let low_again = scheduler.submit(spec("true", 0));
+        let high_again = scheduler.submit(spec("true", 5));
+        assert_eq!(scheduler.queued(), [high, high_again, low, low_again]);
+
+        scheduler.wait_idle();
+        assert_eq!(*started.lock().unwrap(), [blocker, high, high_again, low, low_again]);
+        assert!(scheduler.list().iter().all(|job| job.state == JobState::Succeeded));
+    }
+
+    #[test]
+    fn cancels_queued_and_running_jobs() {
+        let scheduler = Scheduler::new(1);
+        let running = scheduler.submit(sleep("30"));
+        let queued = scheduler.submit(spec("true", 0));
+        wait_started(&scheduler, running);
+
+        assert!(scheduler.cancel(queued));
+        assert_eq!(scheduler.get(queued).unwrap().state, JobState::Cancelled);
+        assert!(scheduler.cancel(running));
+        scheduler.wait_idle();
+
+        let info = scheduler.get(running).unwrap();
+        assert_eq!(info.state, JobState::Cancelled);
+        assert!(info.finished_ms.is_some());
+        assert!(scheduler.get(queued).unwrap().started_ms.is_none());
+        // Finished and unknown jobs cannot be cancelled
+        assert!(!scheduler.cancel(running));
+        assert!(!scheduler.cancel(99));
+    }
+
+    #[test]
+    fn shutdown_stops_running_jobs() {
+        let scheduler = Scheduler::new(2);
+        let ids: Vec<JobId> = (0..3).map(|_| scheduler.submit(sleep("30"))).collect();
+        wait_started(&scheduler, ids[0]);
+        wait_started(&scheduler, ids[1]);
+        scheduler.shutdown();
+        assert!(ids.iter().all(|&id| scheduler.get(id).unwrap().state == JobState::Cancelled));
+    }
+
+    #[test]
+    fn failures_timeouts_and_memory_errors() {
+        let scheduler = Scheduler::new(2);
+        let failed = scheduler.submit(spec("exit 3", 0));
+        let timed_out = scheduler.submit(JobSpec {
+            policy: RunPolicy {
+                timeout_ms: Some(50),
+                ..RunPolicy::default()
+            },
+            ..sleep("30")
+        });
+        let dir = temp_dir("memory");
+        let memory = |script: &str, name: &str| JobSpec {
+            working_dir: Some(dir.join(name)),
+            policy: RunPolicy {
+                memory_bytes: Some(1 << 34),
+                ..RunPolicy::default()
+            },
+            ..spec(script, 0)
+        };
+        let out_of_memory = scheduler.submit(memory("echo MemoryError >&2; exit 1", "failed"));
+        let reported_only = scheduler.submit(memory("echo MemoryError >&2", "succeeded"));
+        scheduler.wait_idle();
+
+        let info = scheduler.get(failed).unwrap();
+        assert_eq!((info.state, info.exit_code, info.limit), (JobState::Failed, Some(3), None));
+        let info = scheduler.get(timed_out).unwrap();
+        assert!(info.state == JobState::Failed && info.timed_out);
+        let info = scheduler.get(out_of_memory).unwrap();
+        assert_eq!((info.state, info.limit), (JobState::Failed, Some(ResourceLimit::Memory)));
+        assert!(fs::read_to_string(info.stderr_path.unwrap()).unwrap().contains("MemoryError"));
+        let info = scheduler.get(reported_only).unwrap();
+        assert_eq!((info.state, info.limit), (JobState::Succeeded, None));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_only_the_tail() {
+        let dir = temp_dir("tail");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stderr.log");
+        fs::write(&path, "MemoryError\n".to_string() + &"x".repeat(100)).unwrap();
+        assert_eq!(read_tail(&path, 4).unwrap(), "xxxx");
+        assert!(read_tail(&path, 1000).unwrap().starts_with("MemoryError"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,634 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::policy::RunPolicy;
+use crate::scheduler::{JobId, JobSpec, JobState, Scheduler};
+
+// Refuse to expand sweeps larger than this, usually a mistyped step
+pub const MAX_POINTS: usize = 100_000;
+
+pub type SweepId = u64;
+
+// Values of one parameter: an explicit list, or a numeric range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AxisValues {
+    List {
+        values: Vec<Value>,
+    },
+    // On a grid, `start..=stop` in steps of `step` or as `count` evenly spaced values
+    // (geometrically spaced with `log`); only one of the two may be given. Random and
+    // Latin hypercube sampling draw from the whole interval and ignore `step` and `count`.
+    Range {
+        start: f64,
+        stop: f64,
+        #[serde(default)]
+        step: Option<f64>,
+        #[serde(default)]
+        count: Option<usize>,
+        #[serde(default)]
+        log: bool,
+    },
+}
+
+// A parameter of the profile, addressed by a dotted path such as `controller.gains.0`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Axis {
+    pub parameter: String,
+    #[serde(flatten)]
+    pub values: AxisValues,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Sampling {
+    // Every combination of the axes' values
+    #[default]
+    Grid,
+    // Independent uniform draws per axis
+    Random {
+        samples: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+    // One draw from each of `samples` strata per axis, strata paired at random
+    LatinHypercube {
+        samples: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+// A sweep over a JSON base profile. Each point gets its own directory under `output_dir`
+// holding the generated `profile.json` and the run's output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepDefinition {
+    pub name: String,
+    pub base_profile: PathBuf,
+    pub axes: Vec<Axis>,
+    #[serde(default)]
+    pub sampling: Sampling,
+    pub output_dir: PathBuf,
+    // Allow a non-empty `output_dir`. If it holds an earlier sweep, that sweep's point
+    // directories are removed with their output and its manifest is replaced.
+    #[serde(default)]
+    pub overwrite: bool,
+    // Passed to every run, as for a single job
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub policy: RunPolicy,
+}
+
+// One concrete combination of parameter values
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepPoint {
+    pub index: usize,
+    pub parameters: BTreeMap<String, Value>,
+    pub profile_path: PathBuf,
+    pub output_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PointStatus {
+    #[serde(flatten)]
+    pub point: SweepPoint,
+    pub job: JobId,
+    // None if the scheduler no longer knows the job
+    pub state: Option<JobState>,
+    pub exit_code: Option<i32>,
+    pub stdout_path: Option<PathBuf>,
+    pub stderr_path: Option<PathBuf>,
+}
+
+// Number of points in each state; `unknown` counts jobs the scheduler no longer has
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SweepCounts {
+    pub queued: usize,
+    pub running: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub unknown: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepStatus {
+    pub id: SweepId,
+    pub name: String,
+    pub output_dir: PathBuf,
+    pub counts: SweepCounts,
+    pub finished: bool,
+    pub points: Vec<PointStatus>,
+}
+
+// SplitMix64: small, seedable and good enough to spread samples
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+fn number(x: f64) -> Result<Value, String> {
+    serde_json::Number::from_f64(x)
+        .map(Value::Number)
+        .ok_or_else(|| format!("Sweep value {} is not a finite number", x))
+}
+
+impl Axis {
+    fn validate(&self) -> Result<(), String> {
+        let fail = |reason: &str| Err(format!("Axis '{}': {}", self.parameter, reason));
+        match &self.values {
+            AxisValues::List { values } if values.is_empty() => fail("values must not be empty"),
+            AxisValues::List { .. } => Ok(()),
+            AxisValues::Range { start, stop, step, count, log } => {
+                if !start.is_finite() || !stop.is_finite() {
+                    fail("start and stop must be finite")
+                } else if *log && (*start <= 0.0 || *stop <= 0.0) {
+                    fail("a log range needs positive start and stop")
+                } else if step.is_some_and(|s| s.is_nan() || s <= 0.0) {
+                    fail("step must be positive")
+                } else if *count == Some(0) {
+                    fail("count must be at least 1")
+                } else if step.is_some() && count.is_some() {
+                    fail("give either step or count, not both")
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    // Values for a grid sweep
+    fn grid(&self) -> Result<Vec<Value>, String> {
+        let (start, stop, step, count, log) = match &self.values {
+            AxisValues::List { values } => return Ok(values.clone()),
+            &AxisValues::Range { start, stop, step, count, log } => (start, stop, step, count, log),
+        };
+        let xs: Vec<f64> = match (count, step) {
+            (Some(1), _) => vec![start],
+            (Some(n), _) if log => {
+                let ratio = (stop / start).ln() / (n - 1) as f64;
+                (0..n).map(|i| start * (ratio * i as f64).exp()).collect()
+            }
+            (Some(n), _) => (0..n).map(|i| start + (stop - start) * i as f64 / (n - 1) as f64).collect(),
+            (None, Some(_)) if log => {
+                return Err(format!("Axis '{}': a log range needs count, not step", self.parameter));
+            }
+            (None, Some(step)) => {
+                // Tolerate round-off so that `stop` itself is included
+                let n = ((stop - start) / step + 1e-9).floor();
+                if n < 0.0 {
+                    return Err(format!("Axis '{}': stop is below start", self.parameter));
+                }
+                if n >= MAX_POINTS as f64 {
+                    return Err(format!("Axis '{}': more than {} values", self.parameter, MAX_POINTS));
+                }
+                (0..=n as usize).map(|i| start + step * i as f64).collect()
+            }
+            (None, None) => {
+                return Err(format!("Axis '{}': a grid range needs step or count", self.parameter));
+            }
+        };
+        xs.into_iter().map(number).collect()
+    }
+
+    // Value at position `u` in [0, 1) along the axis, for random and Latin hypercube sampling
+    fn at(&self, u: f64) -> Result<Value, String> {
+        match &self.values {
+            AxisValues::List { values } => Ok(values[((u * values.len() as f64) as usize).min(values.len() - 1)].clone()),
+            AxisValues::Range { start, stop, log: true, .. } => number((start.ln() + u * (stop / start).ln()).exp()),
+            AxisValues::Range { start, stop, .. } => number(start + u * (stop - start)),
+        }
+    }
+}
+
+// Parameter values of every point, in order
+pub fn expand(definition: &SweepDefinition) -> Result<Vec<BTreeMap<String, Value>>, String> {
+    if definition.axes.is_empty() {
+        return Err("A sweep needs at least one axis".to_string());
+    }
+    for (i, axis) in definition.axes.iter().enumerate() {
+        axis.validate()?;
+        if definition.axes[..i].iter().any(|a| a.parameter == axis.parameter) {
+            return Err(format!("Parameter '{}' appears on more than one axis", axis.parameter));
+        }
+    }
+    let point = |values: Vec<Value>| -> BTreeMap<String, Value> {
+        definition.axes.iter().map(|a| a.parameter.clone()).zip(values).collect()
+    };
+
+    match definition.sampling {
+        Sampling::Grid => {
+            let grids = definition.axes.iter().map(Axis::grid).collect::<Result<Vec<_>, _>>()?;
+            let total = grids.iter().try_fold(1usize, |n, g| n.checked_mul(g.len()).filter(|&n| n <= MAX_POINTS));
+            let Some(total) = total else {
+                return Err(format!("The sweep has more than {} points", MAX_POINTS));
+            };
+            // Mixed radix counter, the last axis varying fastest
+            Ok((0..total)
+                .map(|mut k| {
+                    let mut values = vec![Value::Null; grids.len()];
+                    for (slot, grid) in values.iter_mut().zip(&grids).rev() {
+                        *slot = grid[k % grid.len()].clone();
+                        k /= grid.len();
+                    }
+                    point(values)
+                })
+                .collect())
+        }
+        Sampling::Random { samples, seed } | Sampling::LatinHypercube { samples, seed } => {
+            if samples == 0 || samples > MAX_POINTS {
+                return Err(format!("samples must be between 1 and {}", MAX_POINTS));
+            }
+            let mut rng = Rng(seed);
+            let latin = matches!(definition.sampling, Sampling::LatinHypercube { .. });
+            // u[a][i]: position of sample i along axis a
+            let u: Vec<Vec<f64>> = definition
+                .axes
+                .iter()
+                .map(|_| {
+                    if latin {
+                        let mut strata: Vec<usize> = (0..samples).collect();
+                        rng.shuffle(&mut strata);
+                        strata.into_iter().map(|s| (s as f64 + rng.next_f64()) / samples as f64).collect()
+                    } else {
+                        (0..samples).map(|_| rng.next_f64()).collect()
+                    }
+                })
+                .collect();
+            (0..samples)
+                .map(|i| {
+                    let values = definition.axes.iter().zip(&u).map(|(axis, u)| axis.at(u[i]));
+                    Ok(point(values.collect::<Result<_, String>>()?))
+                })
+                .collect()
+        }
+    }
+}
+
+// Set the value at a dotted path; every segment must already exist in the profile
+fn set_parameter(profile: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    let mut node = profile;
+    for segment in path.split('.') {
+        node = match node {
+            Value::Object(map) => map.get_mut(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Parameter '{}' not found in the base profile", path))?;
+    }
+    *node = value;
+    Ok(())
+}
+
+// Expand the sweep and write each point's profile to `<output_dir>/<index>/profile.json`,
+// along with a `sweep.json` manifest for comparing the results afterwards. Returns the
+// absolute output directory and the points, whose paths are absolute too since each run
+// starts in its own point directory.
+pub fn prepare(definition: &SweepDefinition) -> Result<(PathBuf, Vec<SweepPoint>), String> {
+    let text = fs::read_to_string(&definition.base_profile)
+        .map_err(|e| format!("Failed to read {}: {}", definition.base_profile.display(), e))?;
+    let base: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("{} is not a JSON profile: {}", definition.base_profile.display(), e))?;
+
+    let parameter_sets = expand(definition)?;
+    let mut profiles = Vec::with_capacity(parameter_sets.len());
+    for parameters in &parameter_sets {
+        let mut profile = base.clone();
+        for (path, value) in parameters {
+            set_parameter(&mut profile, path, value.clone())?;
+        }
+        profiles.push(profile);
+    }
+
+    let requested = &definition.output_dir;
+    fs::create_dir_all(requested).map_err(|e| format!("Failed to create {}: {}", requested.display(), e))?;
+    let root = fs::canonicalize(requested).map_err(|e| format!("Failed to resolve {}: {}", requested.display(), e))?;
+    let occupied = fs::read_dir(&root)
+        .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?
+        .next()
+        .is_some();
+    if occupied && !definition.overwrite {
+        return Err(format!("{} is not empty; choose another output directory or allow overwriting", root.display()));
+    }
+    // Only a directory with a manifest holds an earlier sweep whose points can go
+    if occupied && root.join("sweep.json").is_file() {
+        remove_point_dirs(&root)?;
+    }
+
+    let mut points = Vec::new();
+    for (index, (parameters, profile)) in parameter_sets.into_iter().zip(profiles).enumerate() {
+        let output_dir = root.join(format!("{:04}", index));
+        let profile_path = output_dir.join("profile.json");
+        write_json(&profile_path, &profile)?;
+        points.push(SweepPoint {
+            index,
+            parameters,
+            profile_path,
+            output_dir,
+        });
+    }
+
+    let manifest = serde_json::json!({ "definition": definition, "points": points });
+    write_json(&root.join("sweep.json"), &manifest)?;
+    Ok((root, points))
+}
+
+// Remove the numbered point directories of an earlier sweep, so none of its points or
+// their output are mistaken for this sweep's; anything else in `root` is left alone
+fn remove_point_dirs(root: &Path) -> Result<(), String> {
+    let entries = fs::read_dir(root).map_err(|e| format!("Failed to read {}: {}", root.display(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read {}: {}", root.display(), e))?;
+        let name = entry.file_name();
+        let numbered = name.to_str().is_some_and(|n| n.len() >= 4 && n.bytes().all(|b| b.is_ascii_digit()));
+        if numbered && entry.file_type().is_ok_and(|t| t.is_dir()) {
+            let path = entry.path();
+            fs::remove_dir_all(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+struct SweepRecord {
+    name: String,
+    output_dir: PathBuf,
+    points: Vec<(SweepPoint, JobId)>,
+}
+
+// Sweeps started in this session; the state of each point is read from the scheduler
+#[derive(Default)]
+pub struct Sweeps {
+    sweeps: Mutex<BTreeMap<SweepId, SweepRecord>>,
+}
+
+impl Sweeps {
+    // Prepare the sweep and submit one job per point; `job` builds the job that
+    // runs the given point
+    pub fn start(
+        &self,
+        scheduler: &Scheduler,
+        definition: &SweepDefinition,
+        job: impl Fn(&SweepPoint) -> Result<JobSpec, String>,
+    ) -> Result<SweepId, String> {
+        let (output_dir, points) = prepare(definition)?;
+        let specs = points.iter().map(&job).collect::<Result<Vec<_>, _>>()?;
+        let points = points
+            .into_iter()
+            .zip(specs)
+            .map(|(point, spec)| (point, scheduler.submit(spec)))
+            .collect();
+
+        let mut sweeps = self.sweeps.lock().unwrap();
+        let id = sweeps.keys().next_back().map_or(0, |id| id + 1);
+        sweeps.insert(
+            id,
+            SweepRecord {
+                name: definition.name.clone(),
+                output_dir,
+                points,
+            },
+        );
+        Ok(id)
+    }
+
+    pub fn status(&self, id: SweepId, scheduler: &Scheduler) -> Option<SweepStatus> {
+        let sweeps = self.sweeps.lock().unwrap();
+        sweeps.get(&id).map(|record| status(id, record, scheduler))
+    }
+
+    pub fn list(&self, scheduler: &Scheduler) -> Vec<SweepStatus> {
+        let sweeps = self.sweeps.lock().unwrap();
+        sweeps.iter().map(|(&id, record)| status(id, record, scheduler)).collect()
+    }
+
+    // Cancel every unfinished point; returns false if the sweep does not exist
+    pub fn cancel(&self, id: SweepId, scheduler: &Scheduler) -> bool {
+        let sweeps = self.sweeps.lock().unwrap();
+        let Some(record) = sweeps.get(&id) else {
+            return false;
+        };
+        for &(_, job) in &record.points {
+            scheduler.cancel(job);
+        }
+        true
+    }
+}
+
+fn status(id: SweepId, record: &SweepRecord, scheduler: &Scheduler) -> SweepStatus {
+    let points: Vec<PointStatus> = record
+        .points
+        .iter()
+        .map(|(point, job)| {
+            let info = scheduler.get(*job);
+            PointStatus {
+                point: point.clone(),
+                job: *job,
+                state: info.as_ref().map(|i| i.state),
+                exit_code: info.as_ref().and_then(|i| i.exit_code),
+                stdout_path: info.as_ref().and_then(|i| i.stdout_path.clone()),
+                stderr_path: info.and_then(|i| i.stderr_path),
+            }
+        })
+        .collect();
+
+    let mut counts = SweepCounts::default();
+    for point in &points {
+        *match point.state {
+            Some(JobState::Queued) => &mut counts.queued,
+            Some(JobState::Running) => &mut counts.running,
+            Some(JobState::Succeeded) => &mut counts.succeeded,
+            Some(JobState::Failed) => &mut counts.failed,
+            Some(JobState::Cancelled) => &mut counts.cancelled,
+            None => &mut counts.unknown,
+        } += 1;
+    }
+
+    SweepStatus {
+        id,
+        name: record.name.clone(),
+        output_dir: record.output_dir.clone(),
+        counts,
+        finished: points.iter().all(|p| p.state.is_none_or(JobState::is_finished)),
+        points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn range(parameter: &str, start: f64, stop: f64, step: Option<f64>, count: Option<usize>) -> Axis {
+        Axis {
+            parameter: parameter.to_string(),
+            values: AxisValues::Range { start, stop, step, count, log: false },
+        }
+    }
+
+    fn list(parameter: &str, values: Vec<Value>) -> Axis {
+        Axis {
+            parameter: parameter.to_string(),
+            values: AxisValues::List { values },
+        }
+    }
+
+    fn definition(axes: Vec<Axis>, sampling: Sampling, dir: &Path) -> SweepDefinition {
+        SweepDefinition {
+            name: "test".to_string(),
+            base_profile: dir.join("base.json"),
+            axes,
+            sampling,
+            output_dir: dir.join("out"),
+            overwrite: false,
+            args: Vec::new(),
+            priority: 0,
+            policy: RunPolicy::default(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mas-sweep-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("base.json"), r#"{"gain": 1.0, "agents": {"count": 3, "speeds": [1, 2]}}"#).unwrap();
+        dir
+    }
+
+    #[test]
+    fn grid_varies_the_last_axis_fastest() {
+        let axes = vec![
+            range("gain", 0.0, 1.0, Some(0.5), None),
+            list("agents.count", vec![json!(3), json!(4)]),
+        ];
+        let points = expand(&definition(axes, Sampling::Grid, Path::new("."))).unwrap();
+        let pairs: Vec<(f64, i64)> = points
+            .iter()
+            .map(|p| (p["gain"].as_f64().unwrap(), p["agents.count"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(pairs, [(0.0, 3), (0.0, 4), (0.5, 3), (0.5, 4), (1.0, 3), (1.0, 4)]);
+
+        let log = Axis {
+            parameter: "gain".to_string(),
+            values: AxisValues::Range { start: 1.0, stop: 100.0, step: None, count: Some(3), log: true },
+        };
+        let points = expand(&definition(vec![log], Sampling::Grid, Path::new("."))).unwrap();
+        let gains: Vec<f64> = points.iter().map(|p| p["gain"].as_f64().unwrap()).collect();
+        assert!(gains.iter().zip([1.0, 10.0, 100.0]).all(|(g, want)| (g - want).abs() < 1e-9));
+    }
+
+    #[test]
+    fn sampling_stays_in_range_and_is_seeded() {
+        let axes = vec![range("gain", 2.0, 4.0, None, None)];
+        let latin = Sampling::LatinHypercube { samples: 10, seed: 7 };
+        let points = expand(&definition(axes.clone(), latin.clone(), Path::new("."))).unwrap();
+        let mut strata: Vec<usize> = points
+            .iter()
+            .map(|p| ((p["gain"].as_f64().unwrap() - 2.0) / 2.0 * 10.0) as usize)
+            .collect();
+        strata.sort();
+        assert_eq!(strata, (0..10).collect::<Vec<_>>());
+        assert_eq!(points, expand(&definition(axes.clone(), latin, Path::new("."))).unwrap());
+
+        let random = Sampling::Random { samples: 50, seed: 1 };
+        let points = expand(&definition(axes, random, Path::new("."))).unwrap();
+        assert!(points.iter().all(|p| (2.0..4.0).contains(&p["gain"].as_f64().unwrap())));
+    }
+
+    #[test]
+    fn rejects_invalid_axes() {
+        let invalid = [
+            vec![],
+            vec![range("gain", 0.0, 1.0, Some(0.5), Some(3))],
+            vec![range("gain", 0.0, 1.0, None, None)],
+            vec![range("gain", 1.0, 0.0, Some(0.5), None)],
+            vec![range("gain", 0.0, 1.0, Some(1e-9), None)],
+            vec![range("gain", 0.0, 1.0, None, Some(0))],
+            vec![list("gain", vec![])],
+            vec![list("gain", vec![json!(1)]), list("gain", vec![json!(2)])],
+        ];
+        for axes in invalid {
+            assert!(expand(&definition(axes.clone(), Sampling::Grid, Path::new("."))).is_err(), "{:?}", axes);
+        }
+        let both = vec![range("gain", 0.0, 1.0, Some(0.5), Some(3))];
+        assert!(expand(&definition(both, Sampling::Grid, Path::new("."))).unwrap_err().contains("not both"));
+    }
+
+    #[test]
+    fn writes_one_profile_per_point() {
+        let dir = temp_dir("prepare");
+        let axes = vec![list("agents.speeds.1", vec![json!(5), json!(6)])];
+        let (root, points) = prepare(&definition(axes, Sampling::Grid, &dir)).unwrap();
+
+        assert_eq!(root, fs::canonicalize(dir.join("out")).unwrap());
+        assert_eq!(points.len(), 2);
+        let profile: Value = serde_json::from_str(&fs::read_to_string(&points[1].profile_path).unwrap()).unwrap();
+        assert_eq!(profile, json!({"gain": 1.0, "agents": {"count": 3, "speeds": [1, 6]}}));
+        assert_eq!(points[1].output_dir, root.join("0001"));
+        let manifest: Value = serde_json::from_str(&fs::read_to_string(root.join("sweep.json")).unwrap()).unwrap();
+        assert_eq!(manifest["points"].as_array().unwrap().len(), 2);
+
+        let missing = vec![list("agents.mass", vec![json!(1)])];
+        let mut definition = definition(missing, Sampling::Grid, &dir);
+        definition.output_dir = dir.join("missing");
+        assert!(prepare(&definition).unwrap_err().contains("not found"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn overwrite_is_opt_in_and_clears_earlier_points() {
+        let dir = temp_dir("overwrite");
+        let larger = vec![range("gain", 0.0, 1.0, None, Some(3))];
+        prepare(&definition(larger, Sampling::Grid, &dir)).unwrap();
+        fs::write(dir.join("out/0002/trajectory.txt"), "old").unwrap();
+        fs::write(dir.join("out/notes.txt"), "keep").unwrap();
+
+        let smaller = vec![range("gain", 0.0, 1.0, None, Some(2))];
+        let mut definition = definition(smaller, Sampling::Grid, &dir);
+        assert!(prepare(&definition).unwrap_err().contains("not empty"));
+        assert!(dir.join("out/0002").exists());
+
+        definition.overwrite = true;
+        let (root, points) = prepare(&definition).unwrap();
+        assert_eq!(points.len(), 2);
+        assert!(root.join("0001/profile.json").exists());
+        assert!(!root.join("0002").exists());
+        assert!(root.join("notes.txt").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,343 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::collision::closest_pair;
+use crate::frame::Frame;
+use crate::hull::containment;
+use crate::metrics::{radius_metrics, RadiusOptions};
+use crate::parser::non_finite_token;
+
+fn default_percentiles() -> Vec<f64> {
+    vec![5.0, 25.0, 50.0, 75.0, 95.0]
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchOptions {
+    #[serde(flatten)]
+    pub radius: RadiusOptions,
+    /// When set, a run whose agents ever come closer than this does not succeed.
+    #[serde(default)]
+    pub safe_distance: Option<f64>,
+    /// Percentiles (0–100) reported for each metric.
+    #[serde(default = "default_percentiles")]
+    pub percentiles: Vec<f64>,
+}
+
+/// Metrics of one run of a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunMetrics {
+    pub path: String,
+    pub frames: usize,
+    pub convergence_time: Option<f64>,
+    /// Mean `|radius_error|` over the agents in the last frame.
+    pub final_radius_error: f64,
+    /// Smallest distance between two agents over the whole run.
+    pub min_separation: f64,
+    pub containment_ratio: f64,
+    /// Converged, and stayed above the safe distance if one was given.
+    pub success: bool,
+    /// Why the run could not be loaded; all metrics are empty then.
+    pub error: Option<String>,
+}
+
+impl RunMetrics {
+    pub fn compute(path: &str, frames: &[Frame], options: &BatchOptions) -> Self {
+        let radius = radius_metrics(frames, options.radius);
+        let min_separation = frames
+            .iter()
+            .filter_map(|f| closest_pair(&f.state.agents))
+            .map(|p| p.2)
+            .fold(f64::NAN, f64::min);
+        let safe = options
+            .safe_distance
+            .is_none_or(|d| min_separation.is_nan() || min_separation >= d);
+
+        Self {
+            path: path.to_string(),
+            frames: frames.len(),
+            convergence_time: radius.convergence_time,
+            final_radius_error: radius.mean_error.last().copied().unwrap_or(f64::NAN),
+            min_separation,
+            containment_ratio: containment(frames).ratio,
+            success: radius.convergence_time.is_some() && safe,
+            error: None,
+        }
+    }
+
+    pub fn failed(path: &str, error: String) -> Self {
+        Self {
+            path: path.to_string(),
+            frames: 0,
+            convergence_time: None,
+            final_radius_error: f64::NAN,
+            min_separation: f64::NAN,
+            containment_ratio: f64::NAN,
+            success: false,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Percentile {
+    pub p: f64,
+    pub value: f64,
+}
+
+/// Spread of one metric over the runs where it is defined.
+#[derive(Debug, Clone, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f64,
+    /// Sample standard deviation.
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+    pub percentiles: Vec<Percentile>,
+}
+
+impl Distribution {
+    /// `NaN` values are left out. Percentiles interpolate linearly between order statistics.
+    pub fn of(values: impl IntoIterator<Item = f64>, percentiles: &[f64]) -> Self {
+        let mut v: Vec<f64> = values.into_iter().filter(|x| !x.is_nan()).collect();
+        v.sort_by(f64::total_cmp);
+        let n = v.len();
+        let mean = v.iter().sum::<f64>() / n as f64;
+        let std = if n > 1 {
+            (v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+        } else {
+            f64::NAN
+        };
+        let quantile = |p: f64| {
+            if n == 0 {
+                return f64::NAN;
+            }
+            let rank = (p / 100.0).clamp(0.0, 1.0) * (n - 1) as f64;
+            let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+            v[lo] + (rank - lo as f64) * (v[hi] - v[lo])
+        };
+
+        Self {
+            count: n,
+            mean,
+            std,
+            min: v.first().copied().unwrap_or(f64::NAN),
+            max: v.last().copied().unwrap_or(f64::NAN),
+            percentiles: percentiles.iter().map(|&p| Percentile { p, value: quantile(p) }).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchStatistics {
+    pub runs: Vec<RunMetrics>,
+    /// Runs that could not be loaded.
+    pub failed: usize,
+    /// Over the runs that converged.
+    pub convergence_time: Distribution,
+    pub final_radius_error: Distribution,
+    pub min_separation: Distribution,
+    pub containment_ratio: Distribution,
+    /// Fraction of the loaded runs that succeeded.
+    pub success_rate: f64,
+}
+
+pub fn aggregate(runs: Vec<RunMetrics>, percentiles: &[f64]) -> BatchStatistics {
+    let loaded: Vec<&RunMetrics> = runs.iter().filter(|r| r.error.is_none()).collect();
+    let of = |metric: fn(&RunMetrics) -> f64| Distribution::of(loaded.iter().map(|r| metric(r)), percentiles);
+
+    let convergence_time = of(|r| r.convergence_time.unwrap_or(f64::NAN));
+    let final_radius_error = of(|r| r.final_radius_error);
+    let min_separation = of(|r| r.min_separation);
+    let containment_ratio = of(|r| r.containment_ratio);
+    let success_rate = loaded.iter().filter(|r| r.success).count() as f64 / loaded.len() as f64;
+
+    BatchStatistics {
+        failed: runs.len() - loaded.len(),
+        runs,
+        convergence_time,
+        final_radius_error,
+        min_separation,
+        containment_ratio,
+        success_rate,
+    }
+}
+
+/// Writes one delimited field, quoting it when it contains the delimiter, a quote or a newline.
+pub(crate) fn write_field(out: &mut impl Write, field: &str, delimiter: char) -> io::Result<()> {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        write!(out, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        out.write_all(field.as_bytes())
+    }
+}
+
+pub(crate) fn write_row<S: AsRef<str>>(out: &mut impl Write, fields: &[S], delimiter: char) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(out, "{delimiter}")?;
+        }
+        write_field(out, field.as_ref(), delimiter)?;
+    }
+    writeln!(out)
+}
+
+/// Empty for missing values, so spreadsheets see a blank cell rather than `NaN`.
+/// Infinities are written as `Infinity`/`-Infinity`, like the json1 files and pandas.
+/// Adding zero turns `-0` into `0`.
+pub(crate) fn number(v: f64) -> String {
+    if v.is_nan() {
+        String::new()
+    } else if let Some(token) = non_finite_token(v) {
+        token.to_string()
+    } else {
+        (v + 0.0).to_string()
+    }
+}
+
+impl BatchStatistics {
+    /// One row per run.
+    pub fn write_runs_table(&self, out: &mut impl Write, delimiter: char) -> io::Result<()> {
+        let header = [
+            "path",
+            "frames",
+            "convergence_time",
+            "final_radius_error",
+            "min_separation",
+            "containment_ratio",
+            "success",
+            "error",
+        ];
+        write_row(out, &header, delimiter)?;
+        for r in &self.runs {
+            let row = [
+                r.path.clone(),
+                r.frames.to_string(),
+                r.convergence_time.map(number).unwrap_or_default(),
+                number(r.final_radius_error),
+                number(r.min_separation),
+                number(r.containment_ratio),
+                r.success.to_string(),
+                r.error.clone().unwrap_or_default(),
+            ];
+            write_row(out, &row, delimiter)?;
+        }
+        Ok(())
+    }
+
+    /// One row per metric with its distribution.
+    pub fn write_summary_table(&self, out: &mut impl Write, delimiter: char) -> io::Result<()> {
+        let mut header: Vec<String> = ["metric", "count", "mean", "std", "min", "max"].map(String::from).to_vec();
+        header.extend(self.convergence_time.percentiles.iter().map(|p| format!("p{}", p.p)));
+        write_row(out, &header, delimiter)?;
+
+        let metrics = [
+            ("convergence_time", &self.convergence_time),
+            ("final_radius_error", &self.final_radius_error),
+            ("min_separation", &self.min_separation),
+            ("containment_ratio", &self.containment_ratio),
+        ];
+        for (name, d) in metrics {
+            let mut row = vec![
+                name.to_string(),
+                d.count.to_string(),
+                number(d.mean),
+                number(d.std),
+                number(d.min),
+                number(d.max),
+            ];
+            row.extend(d.percentiles.iter().map(|p| number(p.value)));
+            write_row(out, &row, delimiter)?;
+        }
+        write_row(out, &["success_rate".to_string(), number(self.success_rate)], delimiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Point, State};
+
+    fn frame(time: f64, agents: &[Point]) -> Frame {
+        Frame {
+            time,
+            state: State { agents: agents.to_vec(), target: [0.0, 0.0] },
+            signals: Vec::new(),
+        }
+    }
+
+    fn options(safe_distance: Option<f64>) -> BatchOptions {
+        BatchOptions {
+            radius: RadiusOptions { desired_radius: 1.0, tolerance: 0.1 },
+            safe_distance,
+            percentiles: default_percentiles(),
+        }
+    }
+
+    /// Three agents closing in on the unit circle, two of them `gap` apart at the end.
+    fn run(gap: f64) -> Vec<Frame> {
+        vec![
+            frame(0.0, &[[3.0, 0.0], [0.0, 3.0], [-3.0, 0.0]]),
+            frame(1.0, &[[1.0, 0.0], [gap.cos(), gap.sin()], [-1.0, 0.0]]),
+            frame(2.0, &[[1.0, 0.0], [gap.cos(), gap.sin()], [-1.0, 0.0]]),
+        ]
+    }
+
+    #[test]
+    fn percentiles_interpolate_between_order_statistics() {
+        let d = Distribution::of([4.0, f64::NAN, 1.0, 3.0, 2.0], &[0.0, 25.0, 50.0, 90.0, 100.0]);
+        assert_eq!((d.count, d.mean, d.min, d.max), (4, 2.5, 1.0, 4.0));
+        assert!((d.std - (5.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+        let values: Vec<f64> = d.percentiles.iter().map(|p| p.value).collect();
+        assert_eq!(values, vec![1.0, 1.75, 2.5, 3.7, 4.0]);
+
+        let empty = Distribution::of([f64::NAN], &[50.0]);
+        assert_eq!(empty.count, 0);
+        assert!(empty.mean.is_nan() && empty.std.is_nan() && empty.percentiles[0].value.is_nan());
+    }
+
+    #[test]
+    fn success_needs_convergence_and_the_safe_distance() {
+        let spread = RunMetrics::compute("spread", &run(1.5), &options(Some(0.5)));
+        assert_eq!(spread.convergence_time, Some(1.0));
+        assert!(spread.success && spread.error.is_none());
+        assert!(spread.final_radius_error.abs() < 1e-12);
+
+        let crowded = RunMetrics::compute("crowded", &run(0.1), &options(Some(0.5)));
+        assert!(crowded.min_separation < 0.5 && !crowded.success);
+        assert!(RunMetrics::compute("crowded", &run(0.1), &options(None)).success);
+    }
+
+    #[test]
+    fn aggregate_leaves_out_failed_runs() {
+        let runs = vec![
+            RunMetrics::compute("a", &run(1.5), &options(Some(0.5))),
+            RunMetrics::compute("b", &run(0.1), &options(Some(0.5))),
+            RunMetrics::failed("c", "missing".to_string()),
+        ];
+        let stats = aggregate(runs, &[50.0]);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.success_rate, 0.5);
+        assert_eq!(stats.convergence_time.count, 2);
+
+        let mut table = Vec::new();
+        stats.write_runs_table(&mut table, ',').unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("path,frames,convergence_time"));
+        assert_eq!(lines[3], "c,0,,,,,false,missing");
+    }
+
+    #[test]
+    fn fields_are_quoted_when_needed() {
+        let mut out = Vec::new();
+        write_row(&mut out, &["plain", "a,b", "say \"hi\"", "two\nlines"], ',').unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\n");
+        assert_eq!(number(f64::NAN), "");
+        assert_eq!(number(-0.0), "0");
+        assert_eq!(number(0.25), "0.25");
+        assert_eq!(number(f64::NEG_INFINITY), "-Infinity");
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use memmap2::Mmap;
+
+use crate::frame::{Frame, Point, Signal, State};
+use crate::index::source_stamp;
+use crate::parser::{self, non_finite_token, Parsed, RecordError};
+
+const MAGIC: &[u8; 4] = b"FTRJ";
+const VERSION: u32 = 3;
+const HEADER_LEN: usize = 72;
+
+/// Columnar binary cache of a json1 trajectory.
+///
+/// Layout (all integers and floats little-endian, every section 8-byte aligned):
+///
+/// ```text
+/// header           magic "FTRJ", version u32, agent_count u32, reserved u32,
+///                  frame_count, signal_count, rotation_count, distance_len,
+///                  source_len, source_mtime, errors_len (u64 each)
+/// times            f64[frame_count]
+/// targets          f64[frame_count * 2]
+/// agents           f64[frame_count * agent_count * 2]
+/// signal_offsets   u64[frame_count + 1]     frame i owns signals [o[i], o[i+1])
+/// rotation_offsets u64[signal_count + 1]    signal k owns rotations [r[k], r[k+1])
+/// rotations        f64[rotation_count * 2]
+/// distance_offsets u64[signal_count + 1]    byte ranges into the distance blob
+/// distance         u8[distance_len]         each signal's `distance` map as JSON
+/// errors           u8[errors_len]           records skipped by the conversion, as JSON
+/// ```
+///
+/// The file is memory-mapped when read, so opening a cached run costs no parsing.
+/// Every size and offset is checked against the file when it is opened, so a corrupt
+/// cache is rejected rather than read out of bounds.
+/// Converting back with [`BinaryTrajectory::write_json1`] yields frames equal to the
+/// ones the cache was built from.
+#[derive(Debug)]
+pub struct BinaryTrajectory {
+    mmap: Mmap,
+    header: Header,
+    sections: Sections,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    agent_count: usize,
+    frame_count: usize,
+    signal_count: usize,
+    rotation_count: usize,
+    distance_len: usize,
+    source_len: u64,
+    source_mtime: u64,
+    errors_len: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Sections {
+    times: usize,
+    targets: usize,
+    agents: usize,
+    signal_offsets: usize,
+    rotation_offsets: usize,
+    rotations: usize,
+    distance_offsets: usize,
+    distance: usize,
+    errors: usize,
+    end: usize,
+}
+
+impl Header {
+    /// Section starts, or `None` if the sizes overflow.
+    fn sections(&self) -> Option<Sections> {
+        let mut at = HEADER_LEN;
+        let mut take = |words: Option<usize>| -> Option<usize> {
+            let start = at;
+            at = at.checked_add(words?.checked_mul(8)?)?;
+            Some(start)
+        };
+        let frames = self.frame_count;
+        let mut s = Sections {
+            times: take(Some(frames))?,
+            targets: take(frames.checked_mul(2))?,
+            agents: take(frames.checked_mul(self.agent_count).and_then(|n| n.checked_mul(2)))?,
+            signal_offsets: take(frames.checked_add(1))?,
+            rotation_offsets: take(self.signal_count.checked_add(1))?,
+            rotations: take(self.rotation_count.checked_mul(2))?,
+            distance_offsets: take(self.signal_count.checked_add(1))?,
+            ..Default::default()
+        };
+        s.distance = at;
+        s.errors = at.checked_add(self.distance_len)?;
+        s.end = s.errors.checked_add(self.errors_len)?;
+        Some(s)
+    }
+}
+
+/// `realtime.json1` → `realtime.json1.ftrj`
+pub fn cache_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".ftrj");
+    PathBuf::from(name)
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn write_f64s<W: Write>(w: &mut W, values: impl Iterator<Item = f64>) -> io::Result<()> {
+    for v in values {
+        w.write_all(&v.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Write `frames`, and the `errors` met while parsing them, in the binary layout.
+/// Every frame must have the same number of agents.
+pub fn write_binary<W: Write>(
+    w: &mut W,
+    frames: &[Frame],
+    errors: &[RecordError],
+    source_len: u64,
+    source_mtime: u64,
+) -> io::Result<()> {
+    let agent_count = frames.first().map(Frame::agent_count).unwrap_or(0);
+    if let Some(i) = frames.iter().position(|f| f.agent_count() != agent_count) {
+        return Err(invalid(format!(
+            "frame {} has {} agents, expected {}; the binary cache needs a fixed agent count",
+            i,
+            frames[i].agent_count(),
+            agent_count
+        )));
+    }
+
+    let signals: Vec<&Signal> = frames.iter().flat_map(|f| &f.signals).collect();
+    let distances = signals
+        .iter()
+        .map(|s| serde_json::to_vec(&s.distance))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)?;
+    let rotation_count: usize = signals.iter().map(|s| s.rotations.len()).sum();
+    let distance_len: usize = distances.iter().map(Vec::len).sum();
+    let errors = serde_json::to_vec(errors).map_err(io::Error::other)?;
+
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&(agent_count as u32).to_le_bytes())?;
+    w.write_all(&0u32.to_le_bytes())?;
+    for v in [
+        frames.len() as u64,
+        signals.len() as u64,
+        rotation_count as u64,
+        distance_len as u64,
+        source_len,
+        source_mtime,
+        errors.len() as u64,
+    ] {
+        w.write_all(&v.to_le_bytes())?;
+    }
+
+    write_f64s(w, frames.iter().map(|f| f.time))?;
+    write_f64s(w, frames.iter().flat_map(|f| f.state.target))?;
+    write_f64s(w, frames.iter().flat_map(|f| f.state.agents.iter().flatten().copied()))?;
+
+    let mut offset = 0u64;
+    w.write_all(&offset.to_le_bytes())?;
+    for f in frames {
+        offset += f.signals.len() as u64;
+        w.write_all(&offset.to_le_bytes())?;
+    }
+    offset = 0;
+    w.write_all(&offset.to_le_bytes())?;
+    for s in &signals {
+        offset += s.rotations.len() as u64;
+        w.write_all(&offset.to_le_bytes())?;
+    }
+    write_f64s(w, signals.iter().flat_map(|s| s.rotations.iter().flatten().copied()))?;
+    offset = 0;
+    w.write_all(&offset.to_le_bytes())?;
+    for d in &distances {
+        offset += d.len() as u64;
+        w.write_all(&offset.to_le_bytes())?;
+    }
+    for d in &distances {
+        w.write_all(d)?;
+    }
+    w.write_all(&errors)
+}
+
+impl BinaryTrajectory {
+    /// Memory-map an existing cache file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the cache is only ever replaced by writing a new file and renaming it
+        // over the old one, so a mapped file is never modified underneath us.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < HEADER_LEN || &mmap[..4] != MAGIC {
+            return Err(invalid("not a trajectory cache"));
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(mmap[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(mmap[at..at + 8].try_into().unwrap());
+        let size_at = |at: usize| usize::try_from(u64_at(at)).map_err(|_| invalid("trajectory cache header is corrupt"));
+        if u32_at(4) != VERSION {
+            return Err(invalid(format!("unsupported cache version {}", u32_at(4))));
+        }
+
+        let header = Header {
+            agent_count: u32_at(8) as usize,
+            frame_count: size_at(16)?,
+            signal_count: size_at(24)?,
+            rotation_count: size_at(32)?,
+            distance_len: size_at(40)?,
+            source_len: u64_at(48),
+            source_mtime: u64_at(56),
+            errors_len: size_at(64)?,
+        };
+        let sections = header.sections().ok_or_else(|| invalid("trajectory cache header is corrupt"))?;
+        if sections.end != mmap.len() {
+            return Err(invalid("trajectory cache is truncated"));
+        }
+
+        let binary = Self { mmap, header, sections };
+        binary.check_offsets(sections.signal_offsets, header.frame_count, header.signal_count)?;
+        binary.check_offsets(sections.rotation_offsets, header.signal_count, header.rotation_count)?;
+        binary.check_offsets(sections.distance_offsets, header.signal_count, header.distance_len)?;
+        Ok(binary)
+    }
+
+    /// An offset table of `count + 1` entries must start at 0, never decrease and end
+    /// at `total`, which keeps every range it yields inside its section.
+    fn check_offsets(&self, section: usize, count: usize, total: usize) -> io::Result<()> {
+        let mut previous = 0;
+        for i in 0..=count {
+            let offset = self.u64_at(section, i);
+            if (i == 0 && offset != 0) || offset < previous || (i == count && offset != total) {
+                return Err(invalid("trajectory cache offsets are corrupt"));
+            }
+            previous = offset;
+        }
+        Ok(())
+    }
+
+    /// Records the conversion had to skip.
+    pub fn errors(&self) -> io::Result<Vec<RecordError>> {
+        serde_json::from_slice(&self.mmap[self.sections.errors..self.sections.end]).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Open the cache for `json1`, converting it first if the cache is missing, corrupt
+    /// or was built from a different version of the file. Returns the records the
+    /// conversion had to skip, which are kept in the cache.
+    pub fn open_or_convert<P: AsRef<Path>>(json1: P) -> io::Result<(Self, Vec<RecordError>)> {
+        let json1 = json1.as_ref();
+        let cache = cache_path(json1);
+        let (len, mtime) = source_stamp(json1)?;
+
+        if let Ok(binary) = Self::open(&cache)
+            && binary.header.source_len == len
+            && binary.header.source_mtime == mtime
+            && let Ok(errors) = binary.errors()
+        {
+            return Ok((binary, errors));
+        }
+
+        let Parsed { frames, errors } = parser::load_trajectory(json1)?;
+        // Conversions running at the same time each write their own file
+        static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+        let mut tmp = cache.clone().into_os_string();
+        tmp.push(format!(".{}.{}.tmp", std::process::id(), CONVERSIONS.fetch_add(1, Ordering::Relaxed)));
+        let tmp = PathBuf::from(tmp);
+        let written = File::create(&tmp).and_then(|file| {
+            let mut w = BufWriter::new(file);
+            write_binary(&mut w, &frames, &errors, len, mtime)?;
+            w.flush()
+        });
+        if let Err(e) = written.and_then(|()| fs::rename(&tmp, &cache)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        Ok((Self::open(&cache)?, errors))
+    }
+
+    pub fn agent_count(&self) -> usize {
+        self.header.agent_count
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.frame_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.frame_count == 0
+    }
+
+    fn f64_at(&self, section: usize, i: usize) -> f64 {
+        let at = section + i * 8;
+        f64::from_le_bytes(self.mmap[at..at + 8].try_into().unwrap())
+    }
+
+    /// Only called with indices the offset tables were checked against in `open`.
+    fn u64_at(&self, section: usize, i: usize) -> usize {
+        let at = section + i * 8;
+        u64::from_le_bytes(self.mmap[at..at + 8].try_into().unwrap()) as usize
+    }
+
+    fn point_at(&self, section: usize, i: usize) -> Point {
+        [self.f64_at(section, 2 * i), self.f64_at(section, 2 * i + 1)]
+    }
+
+    pub fn time(&self, frame: usize) -> f64 {
+        self.f64_at(self.sections.times, frame)
+    }
+
+    pub fn times(&self) -> Vec<f64> {
+        (0..self.len()).map(|i| self.time(i)).collect()
+    }
+
+    pub fn target(&self, frame: usize) -> Point {
+        self.point_at(self.sections.targets, frame)
+    }
+
+    pub fn agent(&self, frame: usize, agent: usize) -> Point {
+        self.point_at(self.sections.agents, frame * self.header.agent_count + agent)
+    }
+
+    pub fn agents(&self, frame: usize) -> Vec<Point> {
+        (0..self.header.agent_count).map(|j| self.agent(frame, j)).collect()
+    }
+
+    fn signal(&self, k: usize) -> io::Result<Signal> {
+        let rotations = (self.u64_at(self.sections.rotation_offsets, k)
+            ..self.u64_at(self.sections.rotation_offsets, k + 1))
+            .map(|r| self.point_at(self.sections.rotations, r))
+            .collect();
+
+        let start = self.sections.distance + self.u64_at(self.sections.distance_offsets, k);
+        let end = self.sections.distance + self.u64_at(self.sections.distance_offsets, k + 1);
+        let distance: BTreeMap<String, serde_json::Value> =
+            serde_json::from_slice(&self.mmap[start..end]).map_err(|e| invalid(e.to_string()))?;
+
+        Ok(Signal { distance, rotations })
+    }
+
+    /// Rebuild frame `i` exactly as it was in the source file.
+    pub fn frame(&self, i: usize) -> io::Result<Frame> {
+        if i >= self.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("frame {} out of range (0..{})", i, self.len()),
+            ));
+        }
+        let signals = (self.u64_at(self.sections.signal_offsets, i)..self.u64_at(self.sections.signal_offsets, i + 1))
+            .map(|k| self.signal(k))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(Frame {
+            time: self.time(i),
+            state: State {
+                agents: self.agents(i),
+                target: self.target(i),
+            },
+            signals,
+        })
+    }
+
+    pub fn frames(&self) -> io::Result<Vec<Frame>> {
+        (0..self.len()).map(|i| self.frame(i)).collect()
+    }
+
+    /// Write the trajectory back out in the json1 layout `mas` produces.
+    pub fn write_json1<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for i in 0..self.len() {
+            write_record(w, &self.frame(i)?)?;
+            w.write_all(b",\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// One frame as a json1 record. serde_json writes non-finite floats as `null`, so the
+/// coordinates are written here with the tokens the parser reads back.
+fn write_record<W: Write>(w: &mut W, frame: &Frame) -> io::Result<()> {
+    w.write_all(b"{\"time\": ")?;
+    write_number(w, frame.time)?;
+    w.write_all(b", \"state\": {\"agents\": ")?;
+    write_points(w, &frame.state.agents)?;
+    w.write_all(b", \"target\": ")?;
+    write_point(w, frame.state.target)?;
+    w.write_all(b"}, \"signals\": [")?;
+    for (k, signal) in frame.signals.iter().enumerate() {
+        if k > 0 {
+            w.write_all(b", ")?;
+        }
+        w.write_all(b"{\"distance\": ")?;
+        serde_json::to_writer(&mut *w, &signal.distance)?;
+        w.write_all(b", \"rotations\": ")?;
+        write_points(w, &signal.rotations)?;
+        w.write_all(b"}")?;
+    }
+    w.write_all(b"]}")
+}
+
+fn write_points<W: Write>(w: &mut W, points: &[Point]) -> io::Result<()> {
+    w.write_all(b"[")?;
+    for (i, &point) in points.iter().enumerate() {
+        if i > 0 {
+            w.write_all(b", ")?;
+        }
+        write_point(w, point)?;
+    }
+    w.write_all(b"]")
+}
+
+fn write_point<W: Write>(w: &mut W, [x, y]: Point) -> io::Result<()> {
+    w.write_all(b"[")?;
+    write_number(w, x)?;
+    w.write_all(b", ")?;
+    write_number(w, y)?;
+    w.write_all(b"]")
+}
+
+fn write_number<W: Write>(w: &mut W, v: f64) -> io::Result<()> {
+    match non_finite_token(v) {
+        Some(token) => w.write_all(token.as_bytes()),
+        None => Ok(serde_json::to_writer(w, &v)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = concat!(
+        r#"{"time": 0.0, "state": {"agents": [[1.0, 2.0], [3.5, -4.25]], "target": [0.0, 0.5]}, "signals": [{"distance": {"0": {"1": 2.5}}, "rotations": [[0.1, 0.2], [0.3, 0.4]]}]},"#,
+        "\n",
+        "this is not json,\n",
+        r#"{"time": 0.1, "state": {"agents": [[1.1, 2.1], [3.4, -4.2]], "target": [0.1, 0.5]}, "signals": []},"#,
+        "\n",
+        r#"{"time": 0.2, "state": {"agents": [[1.2, 2.2], [3.3, -4.1]], "target": [0.2, 0.5]}, "signals": [{"distance": {}, "rotations": []}, {"distance": {"1": {"0": 1e-3}}, "rotations": [[1.0, 0.0]]}]},"#,
+        "\n",
+        "{\"time\": \n",
+    );
+
+    /// A fresh directory holding `realtime.json1` with `contents`.
+    fn sample_file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mas-binary-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("realtime.json1");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn json1_round_trips_through_the_cache() {
+        let path = sample_file("round-trip", SAMPLE);
+        let original = parser::load_trajectory(&path).unwrap();
+        assert_eq!(original.frames.len(), 3);
+
+        let (binary, errors) = BinaryTrajectory::open_or_convert(&path).unwrap();
+        assert_eq!(errors, original.errors);
+
+        let mut json1 = Vec::new();
+        binary.write_json1(&mut json1).unwrap();
+        let reparsed = parser::parse_reader(json1.as_slice()).unwrap();
+        assert!(reparsed.errors.is_empty());
+        assert_eq!(reparsed.frames, original.frames);
+    }
+
+    #[test]
+    fn cache_hit_reports_the_same_errors() {
+        let path = sample_file("errors", SAMPLE);
+        let (_, first) = BinaryTrajectory::open_or_convert(&path).unwrap();
+        let (_, second) = BinaryTrajectory::open_or_convert(&path).unwrap();
+        assert!(!first.is_empty());
+        assert_eq!(second, first);
+    }
+
+    #[test]
+    fn corrupt_cache_is_rejected_and_rebuilt() {
+        let path = sample_file("corrupt", SAMPLE);
+        let (binary, expected_errors) = BinaryTrajectory::open_or_convert(&path).unwrap();
+        let frames = binary.frames().unwrap();
+        drop(binary);
+
+        let cache = cache_path(&path);
+        let good = fs::read(&cache).unwrap();
+        // Sizes that overflow, and offsets that point past their section
+        let mut huge = good.clone();
+        huge[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        let mut offsets = good.clone();
+        let signal_offsets = HEADER_LEN + 3 * 8 * (1 + 2 + 2 * 2);
+        offsets[signal_offsets + 8..signal_offsets + 16].copy_from_slice(&1000u64.to_le_bytes());
+
+        for bytes in [huge, offsets] {
+            fs::write(&cache, &bytes).unwrap();
+            assert!(BinaryTrajectory::open(&cache).is_err());
+            let (binary, errors) = BinaryTrajectory::open_or_convert(&path).unwrap();
+            assert_eq!(binary.frames().unwrap(), frames);
+            assert_eq!(errors, expected_errors);
+        }
+    }
+
+    #[test]
+    fn non_finite_values_round_trip_through_the_cache() {
+        let contents = concat!(
+            r#"{"time": 0.0, "state": {"agents": [[NaN, Infinity], [-Infinity, 1.5]], "target": [NaN, 0.0]}, "signals": [{"distance": {}, "rotations": [[Infinity, -Infinity]]}]},"#,
+            "\n",
+            r#"{"time": Infinity, "state": {"agents": [[0.0, -0.0], [1e300, -1e-300]], "target": [0.0, -Infinity]}, "signals": []},"#,
+            "\n",
+        );
+        let path = sample_file("non-finite", contents);
+        let original = parser::load_trajectory(&path).unwrap();
+        assert!(original.errors.is_empty());
+        assert!(original.frames[0].state.agents[0][0].is_nan());
+
+        let (binary, _) = BinaryTrajectory::open_or_convert(&path).unwrap();
+        let mut json1 = Vec::new();
+        binary.write_json1(&mut json1).unwrap();
+        assert!(!String::from_utf8_lossy(&json1).contains("null"));
+        let reparsed = parser::parse_reader(json1.as_slice()).unwrap();
+        assert!(reparsed.errors.is_empty());
+        // NaN never compares equal, so compare the printed frames
+        assert_eq!(format!("{:?}", reparsed.frames), format!("{:?}", original.frames));
+    }
+
+    #[test]
+    fn conversion_leaves_no_temporary_files() {
+        let path = sample_file("tmp", SAMPLE);
+        BinaryTrajectory::open_or_convert(&path).unwrap();
+        // A cache that cannot be written fails the conversion and is cleaned up after
+        let cache = cache_path(&path);
+        fs::remove_file(&cache).unwrap();
+        fs::create_dir(&cache).unwrap();
+        assert!(BinaryTrajectory::open_or_convert(&path).is_err());
+
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert!(names.iter().all(|name| !name.ends_with(".tmp")), "{:?}", names);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{Frame, Point};
+use crate::metrics::distance;
+
+/// Separation thresholds below which a frame counts as a violation.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SeparationOptions {
+    /// Minimum allowed distance between two agents.
+    pub agent_distance: f64,
+    /// Minimum allowed distance between an agent and the target.
+    pub target_distance: f64,
+}
+
+/// Who came too close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Proximity {
+    Agents { a: usize, b: usize },
+    Target { agent: usize },
+}
+
+/// A run of consecutive frames in which one pair stayed below its threshold.
+#[derive(Debug, Clone, Serialize)]
+pub struct SeparationEvent {
+    #[serde(flatten)]
+    pub pair: Proximity,
+    pub start: f64,
+    pub end: f64,
+    pub start_frame: usize,
+    pub end_frame: usize,
+    /// Smallest distance reached during the event, and when.
+    pub closest: f64,
+    pub closest_time: f64,
+    pub closest_frame: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeparationAnalysis {
+    pub time: Vec<f64>,
+    /// Smallest distance between any two agents per frame.
+    pub min_separation: Vec<f64>,
+    /// The pair achieving `min_separation`.
+    pub closest_pair: Vec<Option<[usize; 2]>>,
+    /// Smallest agent–target distance per frame.
+    pub min_target_distance: Vec<f64>,
+    pub events: Vec<SeparationEvent>,
+}
+
+/// Cell coordinates up to 2^52 are exact, so neighbouring points land in neighbouring
+/// cells and the keys cannot overflow.
+const MAX_CELL: f64 = (1u64 << 52) as f64;
+
+/// All pairs closer than `threshold`, found by bucketing points into a grid of
+/// `threshold`-sized cells and only comparing neighbouring cells. Points too far out
+/// for the grid relative to `threshold` are compared pairwise instead.
+pub fn pairs_within(points: &[Point], threshold: f64) -> Vec<(usize, usize, f64)> {
+    let mut pairs = Vec::new();
+    if threshold.is_nan() || threshold <= 0.0 || threshold.is_infinite() {
+        return pairs;
+    }
+
+    let finite: Vec<usize> = (0..points.len())
+        .filter(|&i| points[i][0].is_finite() && points[i][1].is_finite())
+        .collect();
+    let scaled = |p: Point| [(p[0] / threshold).floor(), (p[1] / threshold).floor()];
+    if finite.iter().any(|&i| scaled(points[i]).iter().any(|c| c.abs() >= MAX_CELL)) {
+        for (k, &i) in finite.iter().enumerate() {
+            for &j in &finite[k + 1..] {
+                let d = distance(points[i], points[j]);
+                if d < threshold {
+                    pairs.push((i, j, d));
+                }
+            }
+        }
+        return pairs;
+    }
+
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for i in finite {
+        let [cx, cy] = scaled(points[i]);
+        grid.entry((cx as i64, cy as i64)).or_default().push(i);
+    }
+
+    for (&(cx, cy), members) in &grid {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(others) = grid.get(&(cx + dx, cy + dy)) else {
+                    continue;
+                };
+                for &i in members {
+                    for &j in others {
+                        if i < j {
+                            let d = distance(points[i], points[j]);
+                            if d < threshold {
+                                pairs.push((i, j, d));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    pairs.sort_by_key(|&(i, j, _)| (i, j));
+    pairs
+}
+
+/// Closest pair of points by a sweep over x-sorted points, pruned by the best
+/// distance found so far.
+pub fn closest_pair(points: &[Point]) -> Option<(usize, usize, f64)> {
+    let mut order: Vec<usize> = (0..points.len())
+        .filter(|&i| points[i][0].is_finite() && points[i][1].is_finite())
+        .collect();
+    order.sort_by(|&a, &b| points[a][0].total_cmp(&points[b][0]));
+
+    let mut best: Option<(usize, usize, f64)> = None;
+    for (k, &i) in order.iter().enumerate() {
+        for &j in &order[k + 1..] {
+            let bound = best.map_or(f64::INFINITY, |b| b.2);
+            if points[j][0] - points[i][0] >= bound {
+                break;
+            }
+            let d = distance(points[i], points[j]);
+            if d < bound {
+                best = Some((i.min(j), i.max(j), d));
+            }
+        }
+    }
+    best
+}
+
+pub fn separation(frames: &[Frame], options: SeparationOptions) -> SeparationAnalysis {
+    let time: Vec<f64> = frames.iter().map(|f| f.time).collect();
+    let mut min_separation = Vec::with_capacity(frames.len());
+    let mut closest = Vec::with_capacity(frames.len());
+    let mut min_target_distance = Vec::with_capacity(frames.len());
+
+    let mut open: HashMap<Proximity, SeparationEvent> = HashMap::new();
+    let mut events = Vec::new();
+
+    for (k, frame) in frames.iter().enumerate() {
+        let agents = &frame.state.agents;
+        let target = frame.state.target;
+
+        let pair = closest_pair(agents);
+        min_separation.push(pair.map_or(f64::NAN, |p| p.2));
+        closest.push(pair.map(|p| [p.0, p.1]));
+        min_target_distance.push(
+            agents
+                .iter()
+                .map(|&p| distance(p, target))
+                .fold(f64::NAN, f64::min),
+        );
+
+        let mut violations: Vec<(Proximity, f64)> = pairs_within(agents, options.agent_distance)
+            .into_iter()
+            .map(|(a, b, d)| (Proximity::Agents { a, b }, d))
+            .collect();
+        violations.extend(agents.iter().enumerate().filter_map(|(agent, &p)| {
+            let d = distance(p, target);
+            (d < options.target_distance).then_some((Proximity::Target { agent }, d))
+        }));
+
+        let t = frame.time;
+        for &(pair, d) in &violations {
+            let event = open.entry(pair).or_insert_with(|| SeparationEvent {
+                pair,
+                start: t,
+                end: t,
+                start_frame: k,
+                end_frame: k,
+                closest: d,
+                closest_time: t,
+                closest_frame: k,
+            });
+            event.end = t;
+            event.end_frame = k;
+            if d < event.closest {
+                event.closest = d;
+                event.closest_time = t;
+                event.closest_frame = k;
+            }
+        }
+
+        // Close events whose pair is no longer in violation
+        let current: HashSet<Proximity> = violations.iter().map(|v| v.0).collect();
+        let ended: Vec<Proximity> = open.keys().filter(|p| !current.contains(p)).copied().collect();
+        for pair in ended {
+            events.extend(open.remove(&pair));
+        }
+    }
+
+    events.extend(open.into_values());
+    events.sort_by(|a, b| a.start_frame.cmp(&b.start_frame).then(a.closest.total_cmp(&b.closest)));
+
+    SeparationAnalysis {
+        time,
+        min_separation,
+        closest_pair: closest,
+        min_target_distance,
+        events,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::State;
+
+    /// Deterministic points in `[0, scale)²`, clustered enough to give close pairs.
+    fn points(count: usize, scale: f64) -> Vec<Point> {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64 * scale
+        };
+        (0..count).map(|_| [next(), next()]).collect()
+    }
+
+    fn brute_force(points: &[Point], threshold: f64) -> Vec<(usize, usize, f64)> {
+        let mut pairs = Vec::new();
+        for i in 0..points.len() {
+            for j in i + 1..points.len() {
+                let d = distance(points[i], points[j]);
+                if d < threshold {
+                    pairs.push((i, j, d));
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn pairs_match_brute_force() {
+        let mut pts = points(400, 10.0);
+        pts.extend([[-0.25, -0.25], [-0.1, 0.2], [f64::NAN, 1.0], [f64::INFINITY, 0.0]]);
+        for threshold in [0.05, 0.3, 1.0, 20.0] {
+            assert_eq!(pairs_within(&pts, threshold), brute_force(&pts, threshold), "threshold {}", threshold);
+        }
+        assert!(pairs_within(&pts, 0.0).is_empty());
+        assert!(pairs_within(&pts, f64::NAN).is_empty());
+    }
+
+    #[test]
+    fn far_out_points_do_not_overflow_the_grid() {
+        let pts = [[f64::MAX, 0.0], [-f64::MAX, 0.0], [1e300, 1e300], [1e300, 1e300], [0.0, 0.0], [1e-300, 0.0]];
+        assert_eq!(pairs_within(&pts, 1.0), vec![(2, 3, 0.0), (4, 5, 1e-300)]);
+        assert_eq!(pairs_within(&pts, 1e-310), vec![(2, 3, 0.0)]);
+    }
+
+    #[test]
+    fn closest_pair_matches_brute_force() {
+        let pts = points(300, 50.0);
+        let expected = brute_force(&pts, f64::INFINITY)
+            .into_iter()
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .unwrap();
+        assert_eq!(closest_pair(&pts), Some(expected));
+        assert_eq!(closest_pair(&[[0.0, 0.0], [f64::NAN, 0.0]]), None);
+    }
+
+    #[test]
+    fn separation_events_span_consecutive_frames() {
+        let frame = |time: f64, agents: &[Point]| Frame {
+            time,
+            state: State { agents: agents.to_vec(), target: [10.0, 0.0] },
+            signals: Vec::new(),
+        };
+        let frames = [
+            frame(0.0, &[[0.0, 0.0], [5.0, 0.0], [9.0, 0.0]]),
+            frame(1.0, &[[0.0, 0.0], [0.5, 0.0], [9.5, 0.0]]),
+            frame(2.0, &[[0.0, 0.0], [0.2, 0.0], [5.0, 0.0]]),
+            frame(3.0, &[[0.0, 0.0], [3.0, 0.0], [5.0, 0.0]]),
+        ];
+        let analysis = separation(&frames, SeparationOptions { agent_distance: 1.0, target_distance: 2.0 });
+
+        assert_eq!(analysis.min_separation, vec![4.0, 0.5, 0.2, 2.0]);
+        assert_eq!(analysis.closest_pair[1], Some([0, 1]));
+        assert_eq!(analysis.min_target_distance, vec![1.0, 0.5, 5.0, 5.0]);
+
+        let events: Vec<_> = analysis
+            .events
+            .iter()
+            .map(|e| (e.pair, e.start_frame, e.end_frame, e.closest, e.closest_time))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (Proximity::Target { agent: 2 }, 0, 1, 0.5, 1.0),
+                (Proximity::Agents { a: 0, b: 1 }, 1, 2, 0.2, 2.0),
+            ]
+        );
+    }
+}
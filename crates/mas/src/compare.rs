@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{Frame, Point};
+use crate::metrics::distance;
+use crate::resample::{resample_at, Interpolation, TimeGrid};
+use crate::spacing::polar_angle;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CompareOptions {
+    /// Step of the common grid; defaults to the coarser of the two runs' median steps.
+    #[serde(default)]
+    pub dt: Option<f64>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    /// Deviation beyond which the runs count as diverged.
+    pub threshold: f64,
+    /// RMS residual under which the final configurations count as the same.
+    pub match_tolerance: f64,
+}
+
+/// Best alignment of the final formations, relative to their targets.
+#[derive(Debug, Clone, Serialize)]
+pub struct FinalMatch {
+    pub matches: bool,
+    /// RMS distance between matched agents after alignment.
+    pub rms: f64,
+    /// Rotation about the target (radians, counter-clockwise) taking run `a` onto run `b`.
+    pub rotation: f64,
+    /// Agent `i` of run `a` corresponds to agent `permutation[i]` of run `b`.
+    pub permutation: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub time: Vec<f64>,
+    /// Per agent, the distance between its positions in the two runs.
+    pub deviation: Vec<Vec<f64>>,
+    /// RMS and maximum of the deviation over the agents, per sample.
+    pub rms: Vec<f64>,
+    pub max: Vec<f64>,
+    /// First time the maximum deviation exceeds the threshold.
+    pub divergence_time: Option<f64>,
+    /// `None` when the final frames have different (or no) agents.
+    pub final_match: Option<FinalMatch>,
+}
+
+fn median_step(frames: &[Frame]) -> Option<f64> {
+    let mut t: Vec<f64> = frames.iter().map(|f| f.time).filter(|t| t.is_finite()).collect();
+    t.sort_by(f64::total_cmp);
+    let mut steps: Vec<f64> = t.windows(2).map(|w| w[1] - w[0]).filter(|&d| d > 0.0).collect();
+    steps.sort_by(f64::total_cmp);
+    steps.get(steps.len() / 2).copied()
+}
+
+fn time_span(frames: &[Frame]) -> Option<(f64, f64)> {
+    let t = frames.iter().map(|f| f.time).filter(|t| t.is_finite());
+    let first = t.clone().fold(f64::INFINITY, f64::min);
+    let last = t.fold(f64::NEG_INFINITY, f64::max);
+    (first <= last).then_some((first, last))
+}
+
+/// Aligns the final formations up to a rotation about the target and a relabelling
+/// of the agents. Only relabellings that keep the agents' angular order around the
+/// target are tried, each with its least-squares rotation; they match when the RMS
+/// residual is within `tolerance`.
+pub fn match_formations(a: &Frame, b: &Frame, tolerance: f64) -> Option<FinalMatch> {
+    let n = a.agent_count();
+    if n == 0 || n != b.agent_count() {
+        return None;
+    }
+    let relative = |f: &Frame| -> Vec<Point> {
+        f.state
+            .agents
+            .iter()
+            .map(|p| [p[0] - f.state.target[0], p[1] - f.state.target[1]])
+            .collect()
+    };
+    let (ra, rb) = (relative(a), relative(b));
+    let by_angle = |r: &[Point]| {
+        let mut order: Vec<usize> = (0..n).collect();
+        let angle = |i: usize| polar_angle(r[i], [0.0, 0.0]).unwrap_or(0.0);
+        order.sort_by(|&i, &j| angle(i).total_cmp(&angle(j)));
+        order
+    };
+    let (oa, ob) = (by_angle(&ra), by_angle(&rb));
+
+    let mut best: Option<FinalMatch> = None;
+    for shift in 0..n {
+        let mut permutation = vec![0; n];
+        for (k, &i) in oa.iter().enumerate() {
+            permutation[i] = ob[(k + shift) % n];
+        }
+
+        // 2D Procrustes: the rotation maximising Σ b·(R a)
+        let (mut dot, mut cross) = (0.0, 0.0);
+        for (i, &j) in permutation.iter().enumerate() {
+            let (p, q) = (ra[i], rb[j]);
+            dot += p[0] * q[0] + p[1] * q[1];
+            cross += p[0] * q[1] - p[1] * q[0];
+        }
+        let rotation = cross.atan2(dot);
+        let (sin, cos) = rotation.sin_cos();
+        let sum_sq: f64 = permutation
+            .iter()
+            .enumerate()
+            .map(|(i, &j)| {
+                let p = ra[i];
+                distance([cos * p[0] - sin * p[1], sin * p[0] + cos * p[1]], rb[j]).powi(2)
+            })
+            .sum();
+        let rms = (sum_sq / n as f64).sqrt();
+
+        if best.as_ref().is_none_or(|b| rms < b.rms) {
+            best = Some(FinalMatch {
+                matches: rms <= tolerance,
+                rms,
+                rotation,
+                permutation,
+            });
+        }
+    }
+    best
+}
+
+pub fn compare(a: &[Frame], b: &[Frame], options: CompareOptions) -> Result<Comparison, String> {
+    let overlap = time_span(a).zip(time_span(b)).map(|(sa, sb)| (sa.0.max(sb.0), sa.1.min(sb.1)));
+    let dt = options
+        .dt
+        .or_else(|| median_step(a).into_iter().chain(median_step(b)).reduce(f64::max));
+    let grid = match (overlap, dt) {
+        (Some((start, end)), Some(dt)) if start <= end => TimeGrid::Uniform {
+            dt,
+            start: Some(start),
+            end: Some(end),
+        }
+        .times(start, end)?,
+        _ => Vec::new(),
+    };
+
+    let ra = resample_at(a, &grid, options.interpolation);
+    let rb = resample_at(b, &grid, options.interpolation);
+    let time: Vec<f64> = ra.iter().map(|f| f.time).collect();
+
+    let agent_count = ra.iter().zip(&rb).map(|(fa, fb)| fa.agent_count().min(fb.agent_count())).max().unwrap_or(0);
+    let deviation: Vec<Vec<f64>> = (0..agent_count)
+        .map(|j| {
+            ra.iter()
+                .zip(&rb)
+                .map(|(fa, fb)| match (fa.state.agents.get(j), fb.state.agents.get(j)) {
+                    (Some(&p), Some(&q)) => distance(p, q),
+                    _ => f64::NAN,
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut rms = Vec::with_capacity(time.len());
+    let mut max = Vec::with_capacity(time.len());
+    for k in 0..time.len() {
+        let present: Vec<f64> = deviation.iter().map(|d| d[k]).filter(|d| !d.is_nan()).collect();
+        rms.push((present.iter().map(|d| d * d).sum::<f64>() / present.len() as f64).sqrt());
+        max.push(present.iter().copied().fold(f64::NAN, f64::max));
+    }
+    let divergence_time = time.iter().zip(&max).find(|&(_, &m)| m > options.threshold).map(|(&t, _)| t);
+
+    let final_match = a
+        .last()
+        .zip(b.last())
+        .and_then(|(fa, fb)| match_formations(fa, fb, options.match_tolerance));
+
+    Ok(Comparison {
+        time,
+        deviation,
+        rms,
+        max,
+        divergence_time,
+        final_match,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, TAU};
+
+    use super::*;
+    use crate::frame::State;
+
+    fn frame(time: f64, agents: Vec<Point>, target: Point) -> Frame {
+        Frame {
+            time,
+            state: State { agents, target },
+            signals: Vec::new(),
+        }
+    }
+
+    /// Agents evenly spread on a circle of radius 2 around `target`, starting at `phase`.
+    fn ring(count: usize, phase: f64, target: Point) -> Vec<Point> {
+        (0..count)
+            .map(|j| {
+                let a = phase + j as f64 * TAU / count as f64;
+                [target[0] + 2.0 * a.cos(), target[1] + 2.0 * a.sin()]
+            })
+            .collect()
+    }
+
+    fn options(dt: Option<f64>) -> CompareOptions {
+        CompareOptions {
+            dt,
+            interpolation: Interpolation::default(),
+            threshold: 0.5,
+            match_tolerance: 1e-6,
+        }
+    }
+
+    #[test]
+    fn final_formation_matches_up_to_rotation_and_relabelling() {
+        let a = frame(0.0, ring(4, 0.0, [0.0, 0.0]), [0.0, 0.0]);
+        // Same square about another target, turned a quarter plus a bit, with the agents relabelled
+        let mut turned = ring(4, FRAC_PI_2 + 0.1, [5.0, 5.0]);
+        turned.rotate_left(1);
+        let b = frame(0.0, turned, [5.0, 5.0]);
+
+        let m = match_formations(&a, &b, 1e-6).unwrap();
+        assert!(m.matches && m.rms < 1e-9);
+        // The square maps onto itself every quarter turn, so only the remainder is certain
+        assert!((m.rotation.rem_euclid(FRAC_PI_2) - 0.1).abs() < 1e-9);
+        for (i, &j) in m.permutation.iter().enumerate() {
+            let p = a.state.agents[i];
+            let q = b.state.agents[j];
+            let (sin, cos) = m.rotation.sin_cos();
+            let moved = [cos * p[0] - sin * p[1] + 5.0, sin * p[0] + cos * p[1] + 5.0];
+            assert!(distance(moved, q) < 1e-9);
+        }
+
+        let squashed = frame(0.0, vec![[1.0, 0.0], [0.0, 1.0], [-1.0, 0.0], [0.0, -3.0]], [0.0, 0.0]);
+        assert!(!match_formations(&a, &squashed, 1e-6).unwrap().matches);
+        assert!(match_formations(&a, &frame(0.0, ring(3, 0.0, [0.0, 0.0]), [0.0, 0.0]), 1.0).is_none());
+    }
+
+    #[test]
+    fn deviation_over_the_overlapping_time() {
+        let a: Vec<Frame> = (0..=10).map(|k| frame(k as f64, vec![[0.0, 0.0], [1.0, 0.0]], [0.0, 0.0])).collect();
+        // Starts later and drifts away on the second agent
+        let b: Vec<Frame> = (2..=12)
+            .map(|k| frame(k as f64, vec![[0.0, 0.0], [1.0, 0.1 * k as f64]], [0.0, 0.0]))
+            .collect();
+        let c = compare(&a, &b, options(None)).unwrap();
+
+        assert_eq!(c.time, (2..=10).map(f64::from).collect::<Vec<_>>());
+        assert!(c.deviation[0].iter().all(|&d| d == 0.0));
+        assert!((c.deviation[1][3] - 0.5).abs() < 1e-12);
+        assert!((c.rms[3] - (0.25_f64 / 2.0).sqrt()).abs() < 1e-12);
+        assert_eq!(c.divergence_time, Some(6.0));
+        assert!(!c.final_match.unwrap().matches);
+
+        let same = compare(&a, &a, options(Some(0.5))).unwrap();
+        assert_eq!(same.time.len(), 21);
+        assert_eq!(same.divergence_time, None);
+        assert!(same.final_match.unwrap().matches);
+    }
+
+    #[test]
+    fn runs_that_do_not_overlap_give_no_samples() {
+        let a = [frame(0.0, vec![[0.0, 0.0]], [0.0, 0.0]), frame(1.0, vec![[0.0, 0.0]], [0.0, 0.0])];
+        let b = [frame(5.0, vec![[0.0, 0.0]], [0.0, 0.0]), frame(6.0, vec![[0.0, 0.0]], [0.0, 0.0])];
+        let c = compare(&a, &b, options(None)).unwrap();
+        assert!(c.time.is_empty() && c.divergence_time.is_none());
+        assert!(compare(&a, &a, options(Some(-1.0))).is_err());
+    }
+}
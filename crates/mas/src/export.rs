@@ -0,0 +1,323 @@
+use std::io::{self, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::batch::{number, write_row};
+use crate::collision::closest_pair;
+use crate::frame::{Frame, Point};
+use crate::hull::{convex_hull, signed_distance};
+use crate::metrics::distance;
+use crate::parser::{FrameReader, ParseError, RecordError};
+use crate::spacing::polar_angle;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// One row per frame with `agent{i}_x`/`agent{i}_y` columns.
+    #[default]
+    Wide,
+    /// One row per frame and agent.
+    Long,
+}
+
+/// Derived columns that can be added to an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// Agent–target distance.
+    Distance,
+    /// Agent–target distance minus `desired_radius`.
+    RadiusError,
+    /// Agent bearing around the target, in `[0, 2π)`.
+    Bearing,
+    /// Agent speed, by backward difference with the previous frame.
+    Speed,
+    /// Smallest distance between two agents.
+    MinSeparation,
+    /// Signed distance of the target to the agents' hull, positive inside.
+    HullDistance,
+}
+
+impl Metric {
+    fn per_agent(self) -> bool {
+        matches!(self, Metric::Distance | Metric::RadiusError | Metric::Bearing | Metric::Speed)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Metric::Distance => "distance",
+            Metric::RadiusError => "radius_error",
+            Metric::Bearing => "bearing",
+            Metric::Speed => "speed",
+            Metric::MinSeparation => "min_separation",
+            Metric::HullDistance => "hull_distance",
+        }
+    }
+}
+
+fn default_rotations() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub layout: Layout,
+    /// Include the latest rotation vector of each agent.
+    #[serde(default = "default_rotations")]
+    pub rotations: bool,
+    #[serde(default)]
+    pub metrics: Vec<Metric>,
+    /// Needed for [`Metric::RadiusError`]; the column is left blank without it.
+    #[serde(default)]
+    pub desired_radius: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub frames: usize,
+    pub rows: usize,
+    /// Records that could not be parsed and were skipped.
+    pub errors: Vec<RecordError>,
+}
+
+fn point(p: Option<Point>) -> [String; 2] {
+    p.map_or([String::new(), String::new()], |p| [number(p[0]), number(p[1])])
+}
+
+/// Value of a per-agent metric for agent `j` of `frame`.
+fn agent_metric(metric: Metric, frame: &Frame, previous: Option<&Frame>, j: usize, options: &ExportOptions) -> f64 {
+    let Some(&p) = frame.state.agents.get(j) else {
+        return f64::NAN;
+    };
+    let target = frame.state.target;
+    match metric {
+        Metric::Distance => distance(p, target),
+        Metric::RadiusError => options.desired_radius.map_or(f64::NAN, |r| distance(p, target) - r),
+        Metric::Bearing => polar_angle(p, target).unwrap_or(f64::NAN),
+        Metric::Speed => previous
+            .and_then(|prev| Some((prev.state.agents.get(j)?, frame.time - prev.time)))
+            .filter(|&(_, dt)| dt > 0.0)
+            .map_or(f64::NAN, |(&q, dt)| distance(p, q) / dt),
+        Metric::MinSeparation | Metric::HullDistance => f64::NAN,
+    }
+}
+
+fn frame_metric(metric: Metric, frame: &Frame) -> f64 {
+    match metric {
+        Metric::MinSeparation => closest_pair(&frame.state.agents).map_or(f64::NAN, |p| p.2),
+        Metric::HullDistance => signed_distance(frame.state.target, &convex_hull(&frame.state.agents)),
+        _ => f64::NAN,
+    }
+}
+
+fn header(options: &ExportOptions, agent_count: usize) -> Vec<String> {
+    let per_agent = options.metrics.iter().filter(|m| m.per_agent());
+    let per_frame = options.metrics.iter().filter(|m| !m.per_agent()).map(|m| m.name().to_string());
+    let mut header = vec!["time".to_string()];
+    match options.layout {
+        Layout::Wide => {
+            for j in 0..agent_count {
+                header.extend([format!("agent{j}_x"), format!("agent{j}_y")]);
+            }
+            header.extend(["target_x".to_string(), "target_y".to_string()]);
+            if options.rotations {
+                for j in 0..agent_count {
+                    header.extend([format!("rotation{j}_x"), format!("rotation{j}_y")]);
+                }
+            }
+            for m in per_agent {
+                header.extend((0..agent_count).map(|j| format!("agent{j}_{}", m.name())));
+            }
+        }
+        Layout::Long => {
+            header.extend(["agent", "x", "y", "target_x", "target_y"].map(String::from));
+            if options.rotations {
+                header.extend(["rotation_x".to_string(), "rotation_y".to_string()]);
+            }
+            header.extend(per_agent.map(|m| m.name().to_string()));
+        }
+    }
+    header.extend(per_frame);
+    header
+}
+
+/// Writes `frames` as a delimited table, one frame at a time so that arbitrarily
+/// long runs can be exported. Malformed records are skipped and reported. The header
+/// is written even when there are no frames.
+///
+/// The wide layout takes its columns from the first frame and fails on a frame with
+/// a different number of agents; the long layout handles any count.
+pub fn export_table<R: Read, W: Write>(
+    frames: FrameReader<R>,
+    out: &mut W,
+    options: &ExportOptions,
+    delimiter: char,
+) -> io::Result<ExportSummary> {
+    let agent_metrics: Vec<Metric> = options.metrics.iter().copied().filter(|m| m.per_agent()).collect();
+    let frame_metrics: Vec<Metric> = options.metrics.iter().copied().filter(|m| !m.per_agent()).collect();
+
+    let mut summary = ExportSummary {
+        frames: 0,
+        rows: 0,
+        errors: Vec::new(),
+    };
+    // Agent columns of the wide layout, fixed by the first frame
+    let mut columns: Option<usize> = None;
+    let mut previous: Option<Frame> = None;
+
+    for frame in frames {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(ParseError::Malformed(e)) => {
+                summary.errors.push(e);
+                continue;
+            }
+            Err(ParseError::Io(e)) => return Err(e),
+        };
+        let agent_count = *columns.get_or_insert(frame.agent_count());
+        if summary.frames == 0 {
+            write_row(out, &header(options, agent_count), delimiter)?;
+        }
+        if matches!(options.layout, Layout::Wide) && frame.agent_count() != agent_count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "frame at time {} has {} agents but the table has columns for {}; use the long layout",
+                    frame.time,
+                    frame.agent_count(),
+                    agent_count
+                ),
+            ));
+        }
+
+        let rotations = frame.latest_rotations().unwrap_or(&[]);
+        let agents = match options.layout {
+            Layout::Wide => agent_count,
+            Layout::Long => frame.agent_count(),
+        };
+        // values[m][j]: per-agent metric `m` of agent `j`
+        let values: Vec<Vec<String>> = agent_metrics
+            .iter()
+            .map(|&m| (0..agents).map(|j| number(agent_metric(m, &frame, previous.as_ref(), j, options))).collect())
+            .collect();
+        let frame_values: Vec<String> = frame_metrics.iter().map(|&m| number(frame_metric(m, &frame))).collect();
+
+        match options.layout {
+            Layout::Wide => {
+                let mut row = vec![number(frame.time)];
+                for &p in &frame.state.agents {
+                    row.extend(point(Some(p)));
+                }
+                row.extend(point(Some(frame.state.target)));
+                if options.rotations {
+                    for j in 0..agents {
+                        row.extend(point(rotations.get(j).copied()));
+                    }
+                }
+                row.extend(values.into_iter().flatten());
+                row.extend(frame_values);
+                write_row(out, &row, delimiter)?;
+                summary.rows += 1;
+            }
+            Layout::Long => {
+                for j in 0..agents {
+                    let mut row = vec![number(frame.time), j.to_string()];
+                    row.extend(point(Some(frame.state.agents[j])));
+                    row.extend(point(Some(frame.state.target)));
+                    if options.rotations {
+                        row.extend(point(rotations.get(j).copied()));
+                    }
+                    row.extend(values.iter().map(|v| v[j].clone()));
+                    row.extend(frame_values.iter().cloned());
+                    write_row(out, &row, delimiter)?;
+                    summary.rows += 1;
+                }
+            }
+        }
+
+        summary.frames += 1;
+        previous = Some(frame);
+    }
+    if summary.frames == 0 {
+        write_row(out, &header(options, 0), delimiter)?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUN: &str = concat!(
+        r#"{"time": 0.0, "state": {"agents": [[1.0, 0.0], [0.0, 2.0]], "target": [0.0, 0.0]}, "signals": [{"rotations": [[0.5, 0.5]]}]},"#,
+        "\n",
+        "not a record,\n",
+        r#"{"time": 0.5, "state": {"agents": [[2.0, 0.0], [0.0, Infinity]], "target": [0.0, 0.0]}, "signals": []},"#,
+        "\n",
+    );
+
+    fn options(layout: Layout, metrics: Vec<Metric>) -> ExportOptions {
+        ExportOptions {
+            layout,
+            rotations: true,
+            metrics,
+            desired_radius: Some(1.0),
+        }
+    }
+
+    fn export(input: &str, options: &ExportOptions) -> io::Result<(ExportSummary, String)> {
+        let mut out = Vec::new();
+        let summary = export_table(FrameReader::new(input.as_bytes()), &mut out, options, ',')?;
+        Ok((summary, String::from_utf8(out).unwrap()))
+    }
+
+    #[test]
+    fn wide_layout_has_one_row_per_frame() {
+        let metrics = vec![Metric::RadiusError, Metric::Speed, Metric::MinSeparation];
+        let (summary, table) = export(RUN, &options(Layout::Wide, metrics)).unwrap();
+        assert_eq!((summary.frames, summary.rows), (2, 2));
+        assert!(!summary.errors.is_empty());
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "time,agent0_x,agent0_y,agent1_x,agent1_y,target_x,target_y,rotation0_x,rotation0_y,rotation1_x,\
+                 rotation1_y,agent0_radius_error,agent1_radius_error,agent0_speed,agent1_speed,min_separation",
+                "0,1,0,0,2,0,0,0.5,0.5,,,0,1,,,2.23606797749979",
+                "0.5,2,0,0,Infinity,0,0,,,,,1,Infinity,2,Infinity,",
+            ]
+        );
+    }
+
+    #[test]
+    fn long_layout_has_one_row_per_agent() {
+        let (summary, table) = export(RUN, &options(Layout::Long, vec![Metric::Distance])).unwrap();
+        assert_eq!((summary.frames, summary.rows), (2, 4));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "time,agent,x,y,target_x,target_y,rotation_x,rotation_y,distance");
+        assert_eq!(lines[1], "0,0,1,0,0,0,0.5,0.5,1");
+        assert_eq!(lines[4], "0.5,1,0,Infinity,0,0,,,Infinity");
+    }
+
+    #[test]
+    fn wide_layout_rejects_a_changing_agent_count() {
+        let grown = format!(
+            "{}{}",
+            RUN,
+            r#"{"time": 1.0, "state": {"agents": [[1.0, 0.0], [0.0, 2.0], [3.0, 3.0]], "target": [0.0, 0.0]}},"#
+        );
+        let error = export(&grown, &options(Layout::Wide, Vec::new())).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(export(&grown, &options(Layout::Long, Vec::new())).unwrap().0.rows, 7);
+    }
+
+    #[test]
+    fn empty_input_still_gets_a_header() {
+        let (summary, table) = export("", &options(Layout::Wide, vec![Metric::HullDistance])).unwrap();
+        assert_eq!((summary.frames, summary.rows), (0, 0));
+        assert_eq!(table, "time,target_x,target_y,hull_distance\n");
+        let (_, table) = export("garbage,\n", &options(Layout::Long, Vec::new())).unwrap();
+        assert_eq!(table, "time,agent,x,y,target_x,target_y,rotation_x,rotation_y\n");
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A planar position `[x, y]`.
+pub type Point = [f64; 2];
+
+/// One record of a `realtime.json1` trajectory file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    #[serde(deserialize_with = "number")]
+    pub time: f64,
+    pub state: State,
+    #[serde(default)]
+    pub signals: Vec<Signal>,
+}
+
+/// Positions of the agents and the target at one instant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    #[serde(deserialize_with = "points")]
+    pub agents: Vec<Point>,
+    #[serde(deserialize_with = "point")]
+    pub target: Point,
+}
+
+/// Controller signals logged alongside a frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Signal {
+    /// Named distance terms; their shape depends on the controller that produced the run.
+    #[serde(default)]
+    pub distance: BTreeMap<String, serde_json::Value>,
+    /// One rotation vector per agent.
+    #[serde(default, deserialize_with = "points")]
+    pub rotations: Vec<Point>,
+}
+
+impl Frame {
+    pub fn agent_count(&self) -> usize {
+        self.state.agents.len()
+    }
+
+    /// Rotations of the most recent signal entry, if any were logged.
+    pub fn latest_rotations(&self) -> Option<&[Point]> {
+        self.signals.last().map(|s| s.rotations.as_slice())
+    }
+}
+
+/// A coordinate that may also be one of the non-finite tokens Python's `json` writes.
+///
+/// [`parse_frame`](crate::parser::parse_frame) quotes bare `NaN`, `Infinity` and
+/// `-Infinity` so they reach this visitor as strings.
+struct Number(f64);
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl Visitor<'_> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a number, NaN, Infinity or -Infinity")
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Number, E> {
+                Ok(Number(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Number, E> {
+                Ok(Number(v as f64))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Number, E> {
+                Ok(Number(v as f64))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Number, E> {
+                match v {
+                    "NaN" => Ok(Number(f64::NAN)),
+                    "Infinity" => Ok(Number(f64::INFINITY)),
+                    "-Infinity" => Ok(Number(f64::NEG_INFINITY)),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Number::deserialize(deserializer).map(|n| n.0)
+}
+
+fn point<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Point, D::Error> {
+    <[Number; 2]>::deserialize(deserializer).map(|[x, y]| [x.0, y.0])
+}
+
+fn points<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Point>, D::Error> {
+    let points = Vec::<[Number; 2]>::deserialize(deserializer)?;
+    Ok(points.into_iter().map(|[x, y]| [x.0, y.0]).collect())
+}
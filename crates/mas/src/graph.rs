@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+
+use crate::collision::pairs_within;
+use crate::frame::Frame;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct GraphOptions {
+    /// Agents closer than this can communicate.
+    pub radius: f64,
+}
+
+/// A run of consecutive frames in which the graph is disconnected.
+#[derive(Debug, Clone, Serialize)]
+pub struct Disconnection {
+    pub start: f64,
+    pub end: f64,
+    pub start_frame: usize,
+    pub end_frame: usize,
+    /// Most components the graph split into during the interval.
+    pub max_components: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphAnalysis {
+    pub time: Vec<f64>,
+    pub edges: Vec<usize>,
+    pub components: Vec<usize>,
+    pub connected: Vec<bool>,
+    /// Second-smallest eigenvalue of the graph Laplacian; zero when disconnected.
+    pub algebraic_connectivity: Vec<f64>,
+    /// Per frame, the degree of each agent.
+    pub degrees: Vec<Vec<usize>>,
+    pub disconnections: Vec<Disconnection>,
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn component_count(n: usize, edges: &[(usize, usize, f64)]) -> usize {
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut count = n;
+    for &(a, b, _) in edges {
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        if ra != rb {
+            parent[ra] = rb;
+            count -= 1;
+        }
+    }
+    count
+}
+
+/// Eigenvalues of a symmetric matrix by cyclic Jacobi rotations, ascending.
+pub fn symmetric_eigenvalues(mut a: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = a.len();
+    let norm: f64 = a.iter().flatten().map(|v| v * v).sum::<f64>().sqrt();
+    for _ in 0..100 {
+        let off: f64 = (0..n)
+            .flat_map(|p| (p + 1..n).map(move |q| (p, q)))
+            .map(|(p, q)| a[p][q] * a[p][q])
+            .sum::<f64>()
+            .sqrt();
+        if off <= 1e-12 * norm {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                // Rotation in the (p, q) plane that zeroes a[p][q]
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (row_p, row_q) = (a[p].clone(), a[q].clone());
+                for (k, (pk, qk)) in row_p.into_iter().zip(row_q).enumerate() {
+                    a[p][k] = c * pk - s * qk;
+                    a[q][k] = s * pk + c * qk;
+                }
+            }
+        }
+    }
+    let mut eigenvalues: Vec<f64> = (0..n).map(|i| a[i][i]).collect();
+    eigenvalues.sort_by(f64::total_cmp);
+    eigenvalues
+}
+
+/// Fiedler value of the graph on `n` nodes. Only meaningful for connected graphs.
+fn fiedler_value(n: usize, edges: &[(usize, usize, f64)]) -> f64 {
+    let mut laplacian = vec![vec![0.0; n]; n];
+    for &(a, b, _) in edges {
+        laplacian[a][b] -= 1.0;
+        laplacian[b][a] -= 1.0;
+        laplacian[a][a] += 1.0;
+        laplacian[b][b] += 1.0;
+    }
+    symmetric_eigenvalues(laplacian)[1].max(0.0)
+}
+
+pub fn communication_graph(frames: &[Frame], options: GraphOptions) -> GraphAnalysis {
+    let time: Vec<f64> = frames.iter().map(|f| f.time).collect();
+    let mut edge_counts = Vec::with_capacity(frames.len());
+    let mut components = Vec::with_capacity(frames.len());
+    let mut algebraic_connectivity = Vec::with_capacity(frames.len());
+    let mut degrees = Vec::with_capacity(frames.len());
+    let mut disconnections: Vec<Disconnection> = Vec::new();
+
+    for (k, frame) in frames.iter().enumerate() {
+        let n = frame.agent_count();
+        let edges = pairs_within(&frame.state.agents, options.radius);
+
+        let mut degree = vec![0; n];
+        for &(a, b, _) in &edges {
+            degree[a] += 1;
+            degree[b] += 1;
+        }
+        let count = component_count(n, &edges);
+        let lambda = match (n, count) {
+            (0 | 1, _) => f64::NAN,
+            (_, 1) => fiedler_value(n, &edges),
+            _ => 0.0,
+        };
+
+        if count > 1 {
+            match disconnections.last_mut() {
+                Some(d) if d.end_frame + 1 == k => {
+                    d.end = frame.time;
+                    d.end_frame = k;
+                    d.max_components = d.max_components.max(count);
+                }
+                _ => disconnections.push(Disconnection {
+                    start: frame.time,
+                    end: frame.time,
+                    start_frame: k,
+                    end_frame: k,
+                    max_components: count,
+                }),
+            }
+        }
+
+        edge_counts.push(edges.len());
+        components.push(count);
+        algebraic_connectivity.push(lambda);
+        degrees.push(degree);
+    }
+
+    GraphAnalysis {
+        time,
+        edges: edge_counts,
+        connected: components.iter().map(|&c| c <= 1).collect(),
+        components,
+        algebraic_connectivity,
+        degrees,
+        disconnections,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Point, State};
+
+    fn frame(time: f64, agents: &[Point]) -> Frame {
+        Frame {
+            time,
+            state: State { agents: agents.to_vec(), target: [0.0, 0.0] },
+            signals: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn eigenvalues_of_a_known_matrix() {
+        let eigenvalues = symmetric_eigenvalues(vec![
+            vec![2.0, -1.0, 0.0],
+            vec![-1.0, 2.0, -1.0],
+            vec![0.0, -1.0, 2.0],
+        ]);
+        let sqrt2 = 2.0_f64.sqrt();
+        for (v, expected) in eigenvalues.iter().zip([2.0 - sqrt2, 2.0, 2.0 + sqrt2]) {
+            assert!((v - expected).abs() < 1e-10, "{:?}", eigenvalues);
+        }
+    }
+
+    #[test]
+    fn fiedler_values_of_a_path_and_a_complete_graph() {
+        // Path of four agents: 2 - √2
+        let path = frame(0.0, &[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+        let analysis = communication_graph(&[path], GraphOptions { radius: 1.5 });
+        assert_eq!(analysis.edges, vec![3]);
+        assert_eq!(analysis.degrees[0], vec![1, 2, 2, 1]);
+        assert!((analysis.algebraic_connectivity[0] - (2.0 - 2.0_f64.sqrt())).abs() < 1e-10);
+
+        // Within reach of each other the same agents form K4, whose Fiedler value is 4
+        let path = frame(0.0, &[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+        let analysis = communication_graph(&[path], GraphOptions { radius: 10.0 });
+        assert_eq!(analysis.edges, vec![6]);
+        assert!((analysis.algebraic_connectivity[0] - 4.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn disconnections_span_consecutive_frames() {
+        let together = [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]];
+        let split = [[0.0, 0.0], [1.0, 0.0], [9.0, 0.0]];
+        let scattered = [[0.0, 0.0], [5.0, 0.0], [9.0, 0.0]];
+        let frames = [
+            frame(0.0, &together),
+            frame(1.0, &split),
+            frame(2.0, &scattered),
+            frame(3.0, &together),
+            frame(4.0, &split),
+            frame(5.0, &[[0.0, 0.0]]),
+        ];
+        let analysis = communication_graph(&frames, GraphOptions { radius: 1.5 });
+
+        assert_eq!(analysis.components, vec![1, 2, 3, 1, 2, 1]);
+        assert_eq!(analysis.connected, vec![true, false, false, true, false, true]);
+        assert_eq!(analysis.algebraic_connectivity[1], 0.0);
+        assert!(analysis.algebraic_connectivity[5].is_nan());
+        let spans: Vec<_> = analysis
+            .disconnections
+            .iter()
+            .map(|d| (d.start, d.end, d.max_components))
+            .collect();
+        assert_eq!(spans, vec![(1.0, 2.0, 3), (4.0, 4.0, 2)]);
+    }
+}
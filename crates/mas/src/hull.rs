@@ -0,0 +1,174 @@
+use serde::Serialize;
+
+use crate::frame::{Frame, Point};
+use crate::metrics::distance;
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+/// Convex hull in counter-clockwise order (Andrew's monotone chain).
+///
+/// Collinear points on the boundary are dropped. Fewer than three distinct points
+/// give a degenerate hull of one or two vertices.
+pub fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut pts: Vec<Point> = points.iter().copied().filter(|p| p[0].is_finite() && p[1].is_finite()).collect();
+    pts.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    pts.dedup();
+    if pts.len() < 3 {
+        return pts;
+    }
+
+    let mut hull: Vec<Point> = Vec::with_capacity(2 * pts.len());
+    for pass in [pts.clone(), pts.iter().rev().copied().collect()] {
+        let base = hull.len();
+        for p in pass {
+            while hull.len() >= base + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point of each chain is the first point of the other
+        hull.pop();
+    }
+    hull
+}
+
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let len2 = dx * dx + dy * dy;
+    if len2 == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / len2).clamp(0.0, 1.0);
+    distance(p, [a[0] + t * dx, a[1] + t * dy])
+}
+
+/// Distance from `p` to the boundary of `hull`: positive when `p` is strictly inside,
+/// zero on the boundary, negative outside. Degenerate hulls have no inside.
+pub fn signed_distance(p: Point, hull: &[Point]) -> f64 {
+    match hull.len() {
+        0 => f64::NAN,
+        1 => -distance(p, hull[0]),
+        _ => {
+            let n = hull.len();
+            let d = (0..n)
+                .map(|i| segment_distance(p, hull[i], hull[(i + 1) % n]))
+                .fold(f64::INFINITY, f64::min);
+            let inside = n >= 3 && (0..n).all(|i| cross(hull[i], hull[(i + 1) % n], p) > 0.0);
+            if inside { d } else { -d }
+        }
+    }
+}
+
+/// A maximal run of frames with the same containment state.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContainmentInterval {
+    pub start: f64,
+    pub end: f64,
+    pub contained: bool,
+}
+
+/// Whether the target is inside the agents' convex hull over a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Containment {
+    pub time: Vec<f64>,
+    /// Hull vertices per frame, counter-clockwise.
+    pub hulls: Vec<Vec<Point>>,
+    pub contained: Vec<bool>,
+    /// Signed distance of the target to the hull boundary per frame (positive inside).
+    pub signed_distance: Vec<f64>,
+    pub intervals: Vec<ContainmentInterval>,
+    /// Fraction of frames in which the target is contained.
+    pub ratio: f64,
+}
+
+pub fn containment(frames: &[Frame]) -> Containment {
+    let time: Vec<f64> = frames.iter().map(|f| f.time).collect();
+    let hulls: Vec<Vec<Point>> = frames.iter().map(|f| convex_hull(&f.state.agents)).collect();
+    let signed: Vec<f64> = frames
+        .iter()
+        .zip(&hulls)
+        .map(|(f, h)| signed_distance(f.state.target, h))
+        .collect();
+    let contained: Vec<bool> = signed.iter().map(|&d| d > 0.0).collect();
+
+    let mut intervals: Vec<ContainmentInterval> = Vec::new();
+    for (&t, &c) in time.iter().zip(&contained) {
+        match intervals.last_mut() {
+            Some(last) if last.contained == c => last.end = t,
+            _ => intervals.push(ContainmentInterval { start: t, end: t, contained: c }),
+        }
+    }
+
+    let ratio = if frames.is_empty() {
+        f64::NAN
+    } else {
+        contained.iter().filter(|&&c| c).count() as f64 / frames.len() as f64
+    };
+
+    Containment {
+        time,
+        hulls,
+        contained,
+        signed_distance: signed,
+        intervals,
+        ratio,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::State;
+
+    fn frame(time: f64, agents: &[Point], target: Point) -> Frame {
+        Frame {
+            time,
+            state: State { agents: agents.to_vec(), target },
+            signals: Vec::new(),
+        }
+    }
+
+    const SQUARE: [Point; 4] = [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+
+    #[test]
+    fn hull_of_a_square_drops_inner_and_edge_points() {
+        let points = [[1.0, 1.0], [2.0, 2.0], [0.0, 0.0], [1.0, 0.0], [0.0, 2.0], [2.0, 0.0], [0.5, 1.5], [2.0, 2.0]];
+        assert_eq!(convex_hull(&points), SQUARE.to_vec());
+    }
+
+    #[test]
+    fn degenerate_hulls() {
+        assert!(convex_hull(&[]).is_empty());
+        assert_eq!(convex_hull(&[[1.0, 1.0], [1.0, 1.0], [f64::NAN, 0.0]]), vec![[1.0, 1.0]]);
+        assert_eq!(convex_hull(&[[0.0, 0.0], [2.0, 2.0], [1.0, 1.0]]), vec![[0.0, 0.0], [2.0, 2.0]]);
+    }
+
+    #[test]
+    fn signed_distance_is_positive_inside() {
+        assert_eq!(signed_distance([1.0, 0.5], &SQUARE), 0.5);
+        assert_eq!(signed_distance([2.0, 1.0], &SQUARE), 0.0);
+        assert_eq!(signed_distance([5.0, 6.0], &SQUARE), -5.0);
+        assert_eq!(signed_distance([3.0, 4.0], &[[0.0, 0.0]]), -5.0);
+        // A segment has no inside
+        assert_eq!(signed_distance([1.0, 0.0], &[[0.0, 0.0], [2.0, 0.0]]), 0.0);
+        assert!(signed_distance([0.0, 0.0], &[]).is_nan());
+    }
+
+    #[test]
+    fn containment_intervals_and_ratio() {
+        let frames = [
+            frame(0.0, &SQUARE, [5.0, 5.0]),
+            frame(1.0, &SQUARE, [1.0, 1.0]),
+            frame(2.0, &SQUARE, [1.5, 1.0]),
+            frame(3.0, &SQUARE[..2], [1.0, 0.0]),
+        ];
+        let c = containment(&frames);
+        assert_eq!(c.contained, vec![false, true, true, false]);
+        assert_eq!(c.ratio, 0.5);
+        let intervals: Vec<_> = c.intervals.iter().map(|i| (i.start, i.end, i.contained)).collect();
+        assert_eq!(intervals, vec![(0.0, 0.0, false), (1.0, 2.0, true), (3.0, 3.0, false)]);
+        assert!(containment(&[]).ratio.is_nan());
+    }
+}
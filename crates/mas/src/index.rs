@@ -0,0 +1,397 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+
+use crate::frame::Frame;
+use crate::parser::{parse_frame, ParseError, RecordError, RecordScanner};
+
+const INDEX_MAGIC: &[u8; 4] = b"FIDX";
+const INDEX_VERSION: u32 = 3;
+/// Header: magic, version, then source_len, source_mtime, source_inode, indexed_len, count.
+const INDEX_HEADER_LEN: u64 = 8 + 5 * 8;
+const INDEX_ENTRY_LEN: u64 = 3 * 8;
+
+/// Where one frame lives in the json1 file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct IndexEntry {
+    pub offset: u64,
+    pub len: u64,
+    pub time: f64,
+}
+
+/// Frame number → byte range and time for a json1 file.
+///
+/// The index is cached next to the source as `<file>.idx` and is rebuilt when the
+/// source's size, modification time or inode no longer match, or when its indexed
+/// records are no longer where it says they are.
+#[derive(Debug, Clone, Default)]
+pub struct FrameIndex {
+    pub entries: Vec<IndexEntry>,
+    /// Bytes of the source covered by the index (up to the end of the last complete record).
+    pub indexed_len: u64,
+    source_len: u64,
+    source_mtime: u64,
+    source_inode: u64,
+}
+
+/// `realtime.json1` → `realtime.json1.idx`
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".idx");
+    PathBuf::from(name)
+}
+
+/// Size and modification time (ns since the epoch) used to detect stale caches.
+pub(crate) fn source_stamp(path: &Path) -> io::Result<(u64, u64)> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Ok((meta.len(), mtime))
+}
+
+/// Inode of `path`, to notice a file replaced by another one; 0 where there are none.
+pub(crate) fn source_inode(path: &Path) -> io::Result<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(fs::metadata(path)?.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        fs::metadata(path).map(|_| 0)
+    }
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+impl FrameIndex {
+    /// Load the cached index for `path`, or build it (and try to cache it) if it is
+    /// missing or stale.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let (len, mtime) = source_stamp(path)?;
+        let inode = source_inode(path)?;
+        if let Ok(index) = Self::read_sidecar(&sidecar_path(path))
+            && index.source_len == len
+            && index.source_mtime == mtime
+            && index.source_inode == inode
+            && index.matches(path)
+        {
+            return Ok(index);
+        }
+
+        let index = Self::build(path)?;
+        // The source may live in a read-only directory; the index is only a cache.
+        let _ = index.write_sidecar(&sidecar_path(path));
+        Ok(index)
+    }
+
+    pub fn build(path: &Path) -> io::Result<Self> {
+        let mut index = FrameIndex::default();
+        index.extend(path)?;
+        Ok(index)
+    }
+
+    /// Whether the first and last indexed records are still intact in `path`, i.e.
+    /// the indexed part was not rewritten.
+    fn matches(&self, path: &Path) -> bool {
+        let (Some(first), Some(last)) = (self.entries.first(), self.entries.last()) else {
+            return true;
+        };
+        let Ok(mut file) = File::open(path) else {
+            return false;
+        };
+        [first, last].into_iter().all(|entry| {
+            let mut bytes = vec![0; entry.len as usize];
+            file.seek(SeekFrom::Start(entry.offset)).is_ok()
+                && file.read_exact(&mut bytes).is_ok()
+                && parse_frame(&bytes).is_ok_and(|f| f.time.to_bits() == entry.time.to_bits())
+        })
+    }
+
+    /// Index records appended to `path` since the index was built, starting over if
+    /// the file was replaced or rewritten rather than appended to.
+    /// Returns the number of new frames.
+    pub fn extend(&mut self, path: &Path) -> io::Result<usize> {
+        self.update(path).map(|(added, _)| added)
+    }
+
+    /// [`extend`](Self::extend), also reporting whether the index started over.
+    fn update(&mut self, path: &Path) -> io::Result<(usize, bool)> {
+        let (len, mtime) = source_stamp(path)?;
+        let inode = source_inode(path)?;
+        // Appending always grows the file, so a new mtime at the same size is a rewrite
+        let rewritten = len < self.indexed_len
+            || inode != self.source_inode
+            || (len == self.source_len && mtime != self.source_mtime)
+            || !self.matches(path);
+        let reset = rewritten && self.indexed_len > 0;
+        if reset {
+            *self = FrameIndex::default();
+        }
+
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(self.indexed_len))?;
+        let mut reader = BufReader::new(file.take(len - self.indexed_len));
+
+        let before = self.entries.len();
+        let mut scanner = RecordScanner::new();
+        let mut buf = vec![0; 64 * 1024];
+        let mut out = Vec::new();
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            scanner.feed(&buf[..n], &mut out);
+            // Malformed records are left out so every indexed frame can be loaded
+            for record in out.drain(..).flatten() {
+                if let Ok(frame) = record.parse() {
+                    self.entries.push(IndexEntry {
+                        offset: self.indexed_len + record.offset,
+                        len: record.bytes.len() as u64,
+                        time: frame.time,
+                    });
+                }
+            }
+        }
+
+        self.indexed_len += scanner.committed_offset();
+        self.source_len = len;
+        self.source_mtime = mtime;
+        self.source_inode = inode;
+        Ok((self.entries.len() - before, reset))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index of the last frame whose time is not after `time`, clamped to the first frame.
+    pub fn frame_at_time(&self, time: f64) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let after = self.entries.partition_point(|e| e.time <= time);
+        Some(after.saturating_sub(1))
+    }
+
+    fn read_sidecar(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut r = BufReader::new(file);
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        let mut version = [0u8; 4];
+        r.read_exact(&mut version)?;
+        if &magic != INDEX_MAGIC || u32::from_le_bytes(version) != INDEX_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unrecognised index file"));
+        }
+
+        let source_len = read_u64(&mut r)?;
+        let source_mtime = read_u64(&mut r)?;
+        let source_inode = read_u64(&mut r)?;
+        let indexed_len = read_u64(&mut r)?;
+        let count = read_u64(&mut r)?;
+        // A corrupt count must not drive the allocation
+        if count > file_len.saturating_sub(INDEX_HEADER_LEN) / INDEX_ENTRY_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "index entry count exceeds file size"));
+        }
+        if indexed_len > source_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "index covers more than its source"));
+        }
+        let count = count as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let offset = read_u64(&mut r)?;
+            let len = read_u64(&mut r)?;
+            let time = f64::from_bits(read_u64(&mut r)?);
+            // Entries are read back with buffers of their length, so they must lie within the indexed bytes
+            if offset.checked_add(len).is_none_or(|end| end > indexed_len) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "index entry outside the indexed range"));
+            }
+            entries.push(IndexEntry { offset, len, time });
+        }
+
+        Ok(FrameIndex {
+            entries,
+            indexed_len,
+            source_len,
+            source_mtime,
+            source_inode,
+        })
+    }
+
+    fn write_sidecar(&self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(INDEX_MAGIC)?;
+        w.write_all(&INDEX_VERSION.to_le_bytes())?;
+        for v in [self.source_len, self.source_mtime, self.source_inode, self.indexed_len, self.entries.len() as u64] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        for e in &self.entries {
+            w.write_all(&e.offset.to_le_bytes())?;
+            w.write_all(&e.len.to_le_bytes())?;
+            w.write_all(&e.time.to_bits().to_le_bytes())?;
+        }
+        w.flush()
+    }
+}
+
+/// A json1 file opened for random access through its [`FrameIndex`].
+#[derive(Debug)]
+pub struct IndexedTrajectory {
+    path: PathBuf,
+    file: File,
+    index: FrameIndex,
+}
+
+impl IndexedTrajectory {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let index = FrameIndex::open(&path)?;
+        let file = File::open(&path)?;
+        Ok(Self { path, file, index })
+    }
+
+    pub fn index(&self) -> &FrameIndex {
+        &self.index
+    }
+
+    /// Pick up frames written since the file was opened. If the file was replaced or
+    /// rewritten the index starts over and the file is reopened.
+    pub fn refresh(&mut self) -> io::Result<usize> {
+        let (added, reset) = self.index.update(&self.path)?;
+        if reset {
+            self.file = File::open(&self.path)?;
+        }
+        if reset || added > 0 {
+            let _ = self.index.write_sidecar(&sidecar_path(&self.path));
+        }
+        Ok(added)
+    }
+
+    pub fn frame(&mut self, i: usize) -> Result<Frame, ParseError> {
+        let entry = *self.index.entries.get(i).ok_or_else(|| {
+            ParseError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("frame {} out of range (0..{})", i, self.index.len()),
+            ))
+        })?;
+
+        let mut bytes = vec![0; entry.len as usize];
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        self.file.read_exact(&mut bytes)?;
+        parse_frame(&bytes).map_err(|e| {
+            ParseError::Malformed(RecordError {
+                offset: entry.offset,
+                // The index does not track line numbers
+                line: 0,
+                message: e.to_string(),
+            })
+        })
+    }
+
+    /// Frames in `range`, clamped to the frames that exist.
+    pub fn frames(&mut self, range: Range<usize>) -> Result<Vec<Frame>, ParseError> {
+        let end = range.end.min(self.index.len());
+        (range.start.min(end)..end).map(|i| self.frame(i)).collect()
+    }
+
+    pub fn frame_at_time(&self, time: f64) -> Option<usize> {
+        self.index.frame_at_time(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(time: f64) -> String {
+        format!(
+            "{{\"time\": {:?}, \"state\": {{\"agents\": [[{:?}, 0.0]], \"target\": [0.0, 0.0]}}, \"signals\": []}},\n",
+            time, time
+        )
+    }
+
+    /// A fresh `realtime.json1` holding one record per time, with no index next to it.
+    fn sample_file(name: &str, times: &[f64]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mas-index-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("realtime.json1");
+        fs::write(&path, times.iter().map(|&t| record(t)).collect::<String>()).unwrap();
+        path
+    }
+
+    fn times(trajectory: &mut IndexedTrajectory) -> Vec<f64> {
+        trajectory.frames(0..usize::MAX).unwrap().iter().map(|f| f.time).collect()
+    }
+
+    #[test]
+    fn refresh_picks_up_appended_frames() {
+        let path = sample_file("grow", &[0.0, 0.1]);
+        let mut trajectory = IndexedTrajectory::open(&path).unwrap();
+        assert_eq!(times(&mut trajectory), vec![0.0, 0.1]);
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(record(0.2).as_bytes()).unwrap();
+        // Half of the next record is not indexed until the rest arrives
+        file.write_all(b"{\"time\": 0.3,").unwrap();
+        drop(file);
+
+        assert_eq!(trajectory.refresh().unwrap(), 1);
+        assert_eq!(times(&mut trajectory), vec![0.0, 0.1, 0.2]);
+        assert_eq!(trajectory.frame_at_time(0.25), Some(2));
+
+        // The cached index covers the appended frame as well
+        let reopened = FrameIndex::open(&path).unwrap();
+        assert_eq!(reopened.entries, trajectory.index().entries);
+    }
+
+    #[test]
+    fn rewritten_file_starts_the_index_over() {
+        let path = sample_file("rewrite", &[0.0, 0.1, 0.2]);
+        let mut trajectory = IndexedTrajectory::open(&path).unwrap();
+        fs::write(&path, record(5.0) + &record(6.0) + &record(7.0) + &record(8.0)).unwrap();
+
+        trajectory.refresh().unwrap();
+        assert_eq!(times(&mut trajectory), vec![5.0, 6.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    fn corrupt_sidecar_is_rebuilt() {
+        let path = sample_file("corrupt", &[0.0, 0.1, 0.2]);
+        let built = FrameIndex::open(&path).unwrap();
+        let sidecar = sidecar_path(&path);
+
+        // An entry length far beyond the file used to overflow the read buffer's allocation
+        let mut bytes = fs::read(&sidecar).unwrap();
+        let len_at = (INDEX_HEADER_LEN + 8) as usize;
+        bytes[len_at..len_at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&sidecar, &bytes).unwrap();
+        assert!(FrameIndex::read_sidecar(&sidecar).is_err());
+
+        let mut trajectory = IndexedTrajectory::open(&path).unwrap();
+        assert_eq!(trajectory.index().entries, built.entries);
+        assert_eq!(times(&mut trajectory), vec![0.0, 0.1, 0.2]);
+
+        // So does a truncated one
+        fs::write(&sidecar, &bytes[..INDEX_HEADER_LEN as usize + 4]).unwrap();
+        assert_eq!(FrameIndex::open(&path).unwrap().entries, built.entries);
+    }
+}
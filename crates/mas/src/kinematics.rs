@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{Frame, Point};
+
+/// Finite-difference scheme used to differentiate positions. All schemes take the
+/// actual `time` of each frame into account, so steps need not be uniform.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "scheme", rename_all = "snake_case")]
+pub enum DifferenceScheme {
+    /// `(x[i+1] - x[i]) / dt`; the last sample uses the backward difference.
+    Forward,
+    /// Three-point central difference for uneven steps; one-sided at the ends.
+    Central,
+    /// Least-squares polynomial of degree `order` over `window` samples around each point.
+    SavitzkyGolay { window: usize, order: usize },
+}
+
+/// Derivative of `x` with respect to `t`. A zero time step yields `NaN`.
+pub fn differentiate(t: &[f64], x: &[f64], scheme: DifferenceScheme) -> Vec<f64> {
+    let n = t.len().min(x.len());
+    if n < 2 {
+        return vec![f64::NAN; n];
+    }
+    let slope = |i: usize, j: usize| {
+        let dt = t[j] - t[i];
+        if dt == 0.0 { f64::NAN } else { (x[j] - x[i]) / dt }
+    };
+
+    match scheme {
+        DifferenceScheme::Forward => (0..n).map(|i| if i + 1 < n { slope(i, i + 1) } else { slope(i - 1, i) }).collect(),
+        DifferenceScheme::Central => (0..n)
+            .map(|i| {
+                if i == 0 {
+                    slope(0, 1)
+                } else if i == n - 1 {
+                    slope(n - 2, n - 1)
+                } else {
+                    let h1 = t[i] - t[i - 1];
+                    let h2 = t[i + 1] - t[i];
+                    if h1 == 0.0 || h2 == 0.0 {
+                        return f64::NAN;
+                    }
+                    -h2 / (h1 * (h1 + h2)) * x[i - 1] + (h2 - h1) / (h1 * h2) * x[i] + h1 / (h2 * (h1 + h2)) * x[i + 1]
+                }
+            })
+            .collect(),
+        DifferenceScheme::SavitzkyGolay { window, order } => {
+            let window = window.max(order + 1).min(n).max(2);
+            (0..n)
+                .map(|i| {
+                    let lo = i.saturating_sub(window / 2).min(n - window);
+                    polyfit_slope(&t[lo..lo + window], &x[lo..lo + window], t[i], order.min(window - 1))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Slope at `t0` of the least-squares polynomial of degree `order` through the samples.
+fn polyfit_slope(t: &[f64], x: &[f64], t0: f64, order: usize) -> f64 {
+    let m = order + 1;
+    // Centre and scale the abscissa to keep the normal equations well conditioned
+    let scale = t.iter().map(|&ti| (ti - t0).abs()).fold(0.0, f64::max);
+    if scale == 0.0 {
+        return f64::NAN;
+    }
+
+    let mut ata = vec![vec![0.0; m]; m];
+    let mut aty = vec![0.0; m];
+    for (&ti, &xi) in t.iter().zip(x) {
+        let tau = (ti - t0) / scale;
+        let powers: Vec<f64> = (0..m).map(|p| tau.powi(p as i32)).collect();
+        for r in 0..m {
+            aty[r] += powers[r] * xi;
+            for c in 0..m {
+                ata[r][c] += powers[r] * powers[c];
+            }
+        }
+    }
+
+    match solve(ata, aty) {
+        Some(coef) if m > 1 => coef[1] / scale,
+        _ => f64::NAN,
+    }
+}
+
+/// Gaussian elimination with partial pivoting.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col].clone();
+        for row in col + 1..n {
+            let f = a[row][col] / pivot_row[col];
+            for (v, p) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *v -= f * p;
+            }
+            b[row] -= f * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let s: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - s) / a[row][row];
+    }
+    Some(x)
+}
+
+fn differentiate_points(t: &[f64], p: &[Point], scheme: DifferenceScheme) -> Vec<Point> {
+    let xs: Vec<f64> = p.iter().map(|q| q[0]).collect();
+    let ys: Vec<f64> = p.iter().map(|q| q[1]).collect();
+    differentiate(t, &xs, scheme)
+        .into_iter()
+        .zip(differentiate(t, &ys, scheme))
+        .map(|(x, y)| [x, y])
+        .collect()
+}
+
+fn norm(p: &Point) -> f64 {
+    p[0].hypot(p[1])
+}
+
+/// Trapezoidal `∫ y dt` over uneven steps, skipping intervals with `NaN` ends.
+pub fn integrate(t: &[f64], y: &[f64]) -> f64 {
+    t.windows(2)
+        .zip(y.windows(2))
+        .map(|(t, y)| 0.5 * (y[0] + y[1]) * (t[1] - t[0]))
+        .filter(|v| !v.is_nan())
+        .sum()
+}
+
+/// Derived motion of one body.
+#[derive(Debug, Clone, Serialize)]
+pub struct MotionSeries {
+    pub velocity: Vec<Point>,
+    pub speed: Vec<f64>,
+    /// Direction of travel, `atan2(vy, vx)`.
+    pub heading: Vec<f64>,
+    pub acceleration: Vec<Point>,
+    pub acceleration_norm: Vec<f64>,
+    pub jerk: Vec<Point>,
+    pub jerk_norm: Vec<f64>,
+}
+
+impl MotionSeries {
+    pub fn from_positions(t: &[f64], positions: &[Point], scheme: DifferenceScheme) -> Self {
+        let velocity = differentiate_points(t, positions, scheme);
+        let acceleration = differentiate_points(t, &velocity, scheme);
+        let jerk = differentiate_points(t, &acceleration, scheme);
+        Self {
+            speed: velocity.iter().map(norm).collect(),
+            heading: velocity.iter().map(|v| v[1].atan2(v[0])).collect(),
+            acceleration_norm: acceleration.iter().map(norm).collect(),
+            jerk_norm: jerk.iter().map(norm).collect(),
+            velocity,
+            acceleration,
+            jerk,
+        }
+    }
+
+    /// `∫ ‖a‖² dt`, a proxy for the control effort spent.
+    pub fn control_effort(&self, t: &[f64]) -> f64 {
+        let a2: Vec<f64> = self.acceleration_norm.iter().map(|a| a * a).collect();
+        integrate(t, &a2)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Kinematics {
+    pub time: Vec<f64>,
+    pub agents: Vec<MotionSeries>,
+    pub target: MotionSeries,
+    /// `∫ ‖a‖² dt` per agent.
+    pub control_effort: Vec<f64>,
+}
+
+pub fn kinematics(frames: &[Frame], scheme: DifferenceScheme) -> Kinematics {
+    let agent_count = frames.iter().map(Frame::agent_count).max().unwrap_or(0);
+    let time: Vec<f64> = frames.iter().map(|f| f.time).collect();
+
+    let agents: Vec<MotionSeries> = (0..agent_count)
+        .map(|j| {
+            let positions: Vec<Point> = frames
+                .iter()
+                .map(|f| f.state.agents.get(j).copied().unwrap_or([f64::NAN; 2]))
+                .collect();
+            MotionSeries::from_positions(&time, &positions, scheme)
+        })
+        .collect();
+    let target_positions: Vec<Point> = frames.iter().map(|f| f.state.target).collect();
+    let target = MotionSeries::from_positions(&time, &target_positions, scheme);
+    let control_effort = agents.iter().map(|a| a.control_effort(&time)).collect();
+
+    Kinematics {
+        time,
+        agents,
+        target,
+        control_effort,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::State;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    /// Uneven sample times, so every scheme has to use the actual steps.
+    fn uneven_times() -> Vec<f64> {
+        vec![0.0, 0.1, 0.3, 0.35, 0.6, 1.0, 1.1, 1.5]
+    }
+
+    #[test]
+    fn every_scheme_is_exact_on_a_line() {
+        let t = uneven_times();
+        let x: Vec<f64> = t.iter().map(|t| 3.0 * t - 1.0).collect();
+        for scheme in [
+            DifferenceScheme::Forward,
+            DifferenceScheme::Central,
+            DifferenceScheme::SavitzkyGolay { window: 5, order: 2 },
+        ] {
+            assert!(differentiate(&t, &x, scheme).iter().all(|&v| close(v, 3.0)), "{:?}", scheme);
+        }
+    }
+
+    #[test]
+    fn central_and_savitzky_golay_are_exact_on_a_parabola() {
+        let t = uneven_times();
+        let x: Vec<f64> = t.iter().map(|t| t * t).collect();
+        let central = differentiate(&t, &x, DifferenceScheme::Central);
+        // Interior points only; the ends fall back to one-sided differences
+        for i in 1..t.len() - 1 {
+            assert!(close(central[i], 2.0 * t[i]), "{} at {}", central[i], t[i]);
+        }
+        let smooth = differentiate(&t, &x, DifferenceScheme::SavitzkyGolay { window: 4, order: 2 });
+        assert!(smooth.iter().zip(&t).all(|(&v, &t)| close(v, 2.0 * t)));
+    }
+
+    #[test]
+    fn degenerate_steps_give_nan() {
+        assert!(differentiate(&[0.0], &[1.0], DifferenceScheme::Forward)[0].is_nan());
+        let v = differentiate(&[0.0, 1.0, 1.0], &[0.0, 1.0, 2.0], DifferenceScheme::Forward);
+        assert!(close(v[0], 1.0) && v[1].is_nan() && v[2].is_nan());
+    }
+
+    #[test]
+    fn integrate_uses_the_trapezoid_rule() {
+        assert!(close(integrate(&[0.0, 1.0, 3.0], &[0.0, 2.0, 2.0]), 5.0));
+        assert!(close(integrate(&[0.0, 1.0, 2.0, 3.0], &[1.0, f64::NAN, 1.0, 1.0]), 1.0));
+    }
+
+    #[test]
+    fn uniform_circular_motion() {
+        let frames: Vec<Frame> = (0..200)
+            .map(|k| {
+                let t = k as f64 * 0.01;
+                Frame {
+                    time: t,
+                    state: State { agents: vec![[2.0 * t.cos(), 2.0 * t.sin()]], target: [t, 0.0] },
+                    signals: Vec::new(),
+                }
+            })
+            .collect();
+        let k = kinematics(&frames, DifferenceScheme::SavitzkyGolay { window: 7, order: 3 });
+
+        let agent = &k.agents[0];
+        let mid = 100;
+        assert!((agent.speed[mid] - 2.0).abs() < 1e-6);
+        assert!((agent.acceleration_norm[mid] - 2.0).abs() < 1e-4);
+        assert!((agent.heading[mid] - (1.0 + std::f64::consts::FRAC_PI_2)).abs() < 1e-6);
+        assert!(k.target.speed.iter().all(|&s| close(s, 1.0)));
+        // ‖a‖² = 4 over t ∈ [0, 1.99]
+        assert!((k.control_effort[0] - 4.0 * 1.99).abs() < 1e-2);
+    }
+}
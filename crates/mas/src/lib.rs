@@ -0,0 +1,41 @@
+//! Data model and analysis for trajectories produced by `mas simulate`.
+
+pub mod batch;
+pub mod binary;
+pub mod collision;
+pub mod compare;
+pub mod export;
+pub mod frame;
+pub mod graph;
+pub mod hull;
+pub mod index;
+pub mod kinematics;
+pub mod metrics;
+pub mod parser;
+pub mod resample;
+pub mod rotation;
+pub mod spacing;
+pub mod spectrum;
+pub mod summary;
+pub mod tail;
+pub mod validate;
+
+pub use batch::{aggregate, BatchOptions, BatchStatistics, Distribution, RunMetrics};
+pub use binary::BinaryTrajectory;
+pub use collision::{separation, SeparationAnalysis, SeparationEvent, SeparationOptions};
+pub use compare::{compare, CompareOptions, Comparison, FinalMatch};
+pub use export::{export_table, ExportOptions, ExportSummary, Layout, Metric};
+pub use frame::{Frame, Point, Signal, State};
+pub use graph::{communication_graph, Disconnection, GraphAnalysis, GraphOptions};
+pub use hull::{containment, Containment};
+pub use index::{FrameIndex, IndexEntry, IndexedTrajectory};
+pub use kinematics::{kinematics, DifferenceScheme, Kinematics};
+pub use metrics::{radius_metrics, RadiusMetrics, RadiusOptions};
+pub use parser::{load_trajectory, FrameReader, ParseError, Parsed, RecordError, RecordScanner};
+pub use resample::{resample, resample_at, Interpolation, ResampleOptions, TimeGrid};
+pub use rotation::{formation_rotation, RotationAnalysis, RotationOptions};
+pub use spacing::{angular_spacing, SpacingAnalysis};
+pub use spectrum::{spectral_analysis, SpectralAnalysis, SpectrumOptions};
+pub use summary::{summarize, RunSummary};
+pub use tail::{TailBatch, TailFollower};
+pub use validate::{validate, Issue, IssueKind, ValidationOptions, ValidationReport};
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{Frame, Point};
+
+pub(crate) fn distance(a: Point, b: Point) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+/// Parameters of the encirclement metrics.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RadiusOptions {
+    /// Radius the controller is trying to hold around the target.
+    pub desired_radius: f64,
+    /// An agent counts as converged once its radius error stays below this.
+    pub tolerance: f64,
+}
+
+/// Time series for one agent.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentRadiusSeries {
+    /// Distance to the target per frame (`NaN` where the agent is missing).
+    pub distance: Vec<f64>,
+    /// `distance - desired_radius` per frame.
+    pub radius_error: Vec<f64>,
+    pub convergence_time: Option<f64>,
+}
+
+/// Encirclement quality of a run, frame by frame.
+#[derive(Debug, Clone, Serialize)]
+pub struct RadiusMetrics {
+    pub time: Vec<f64>,
+    pub agents: Vec<AgentRadiusSeries>,
+    /// Mean of `|radius_error|` over the agents present in each frame.
+    pub mean_error: Vec<f64>,
+    /// Max of `|radius_error|` over the agents present in each frame.
+    pub max_error: Vec<f64>,
+    /// First time after which `max_error` stays below the tolerance.
+    pub convergence_time: Option<f64>,
+}
+
+/// First `time[k]` such that `error[j] < tolerance` for every `j >= k`.
+///
+/// `NaN` errors count as not converged.
+pub fn convergence_time(time: &[f64], error: &[f64], tolerance: f64) -> Option<f64> {
+    let mut first = None;
+    for k in (0..error.len().min(time.len())).rev() {
+        if error[k].abs() < tolerance {
+            first = Some(k);
+        } else {
+            break;
+        }
+    }
+    first.map(|k| time[k])
+}
+
+pub fn radius_metrics(frames: &[Frame], options: RadiusOptions) -> RadiusMetrics {
+    let agent_count = frames.iter().map(Frame::agent_count).max().unwrap_or(0);
+    let time: Vec<f64> = frames.iter().map(|f| f.time).collect();
+
+    let mut agents: Vec<AgentRadiusSeries> = (0..agent_count)
+        .map(|_| AgentRadiusSeries {
+            distance: Vec::with_capacity(frames.len()),
+            radius_error: Vec::with_capacity(frames.len()),
+            convergence_time: None,
+        })
+        .collect();
+    let mut mean_error = Vec::with_capacity(frames.len());
+    let mut max_error = Vec::with_capacity(frames.len());
+
+    for frame in frames {
+        let target = frame.state.target;
+        let mut sum = 0.0;
+        let mut max = f64::NAN;
+        for (j, series) in agents.iter_mut().enumerate() {
+            let d = frame.state.agents.get(j).map_or(f64::NAN, |&p| distance(p, target));
+            let e = d - options.desired_radius;
+            series.distance.push(d);
+            series.radius_error.push(e);
+            if !e.is_nan() {
+                sum += e.abs();
+                max = max.max(e.abs());
+            }
+        }
+        let present = frame.agent_count().min(agent_count);
+        mean_error.push(if present > 0 { sum / present as f64 } else { f64::NAN });
+        max_error.push(max);
+    }
+
+    for series in &mut agents {
+        series.convergence_time = convergence_time(&time, &series.radius_error, options.tolerance);
+    }
+    let convergence = convergence_time(&time, &max_error, options.tolerance);
+
+    RadiusMetrics {
+        time,
+        agents,
+        mean_error,
+        max_error,
+        convergence_time: convergence,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::State;
+
+    fn frame(time: f64, agents: &[Point]) -> Frame {
+        Frame {
+            time,
+            state: State { agents: agents.to_vec(), target: [1.0, 1.0] },
+            signals: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn convergence_time_is_where_the_error_stays_below_tolerance() {
+        let time = [0.0, 1.0, 2.0, 3.0, 4.0];
+        assert_eq!(convergence_time(&time, &[5.0, 0.1, 2.0, 0.1, -0.1], 0.5), Some(3.0));
+        assert_eq!(convergence_time(&time, &[0.1; 5], 0.5), Some(0.0));
+        assert_eq!(convergence_time(&time, &[0.1, 0.1, 0.1, 0.1, 1.0], 0.5), None);
+        assert_eq!(convergence_time(&time, &[0.1, 0.1, 0.1, 0.1, f64::NAN], 0.5), None);
+    }
+
+    #[test]
+    fn radius_errors_per_agent_and_overall() {
+        let frames = [
+            frame(0.0, &[[3.0, 1.0], [1.0, 5.0]]),
+            frame(1.0, &[[1.0, 3.0], [1.0, 3.5]]),
+            frame(2.0, &[[-1.0, 1.0]]),
+        ];
+        let metrics = radius_metrics(&frames, RadiusOptions { desired_radius: 2.0, tolerance: 0.6 });
+
+        assert_eq!(metrics.time, vec![0.0, 1.0, 2.0]);
+        assert_eq!(metrics.agents[0].distance, vec![2.0, 2.0, 2.0]);
+        assert_eq!(metrics.agents[1].radius_error[..2], [2.0, 0.5]);
+        // The second agent is missing from the last frame
+        assert!(metrics.agents[1].distance[2].is_nan());
+        assert_eq!(metrics.mean_error, vec![1.0, 0.25, 0.0]);
+        assert_eq!(metrics.max_error, vec![2.0, 0.5, 0.0]);
+
+        assert_eq!(metrics.agents[0].convergence_time, Some(0.0));
+        assert_eq!(metrics.agents[1].convergence_time, None);
+        assert_eq!(metrics.convergence_time, Some(1.0));
+    }
+
+    #[test]
+    fn no_frames_give_empty_metrics() {
+        let metrics = radius_metrics(&[], RadiusOptions { desired_radius: 1.0, tolerance: 0.1 });
+        assert!(metrics.agents.is_empty() && metrics.time.is_empty());
+        assert_eq!(metrics.convergence_time, None);
+    }
+}
@@ -0,0 +1,319 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::frame::Frame;
+
+/// A record that could not be turned into a [`Frame`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordError {
+    /// Byte offset of the start of the record in the file.
+    pub offset: u64,
+    /// 1-based line on which the record starts.
+    pub line: u64,
+    pub message: String,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} (byte {}): {}", self.line, self.offset, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    Malformed(RecordError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "{}", e),
+            ParseError::Malformed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+/// The raw bytes of one top-level JSON object and where it started.
+#[derive(Debug, Clone)]
+pub struct RawRecord {
+    pub offset: u64,
+    pub line: u64,
+    pub bytes: Vec<u8>,
+}
+
+impl RawRecord {
+    pub fn parse(&self) -> Result<Frame, RecordError> {
+        parse_frame(&self.bytes).map_err(|e| RecordError {
+            offset: self.offset,
+            line: self.line + e.line().saturating_sub(1) as u64,
+            message: e.to_string(),
+        })
+    }
+}
+
+/// Deserialize one record, accepting the `NaN`, `Infinity` and `-Infinity` tokens
+/// Python's `json` writes for non-finite floats.
+pub(crate) fn parse_frame(bytes: &[u8]) -> serde_json::Result<Frame> {
+    serde_json::from_slice(&quote_non_finite(bytes))
+}
+
+/// The token Python's `json` writes for a non-finite float, which [`parse_frame`] reads back.
+pub(crate) fn non_finite_token(v: f64) -> Option<&'static str> {
+    if v.is_nan() {
+        Some("NaN")
+    } else if v == f64::INFINITY {
+        Some("Infinity")
+    } else if v == f64::NEG_INFINITY {
+        Some("-Infinity")
+    } else {
+        None
+    }
+}
+
+/// Wrap bare non-finite tokens outside strings in quotes so they become valid JSON.
+/// Newlines are kept as they are, so error positions still refer to the original bytes.
+fn quote_non_finite(bytes: &[u8]) -> Cow<'_, [u8]> {
+    const TOKENS: [&[u8]; 3] = [b"NaN", b"Infinity", b"-Infinity"];
+
+    let mut out: Option<Vec<u8>> = None;
+    let (mut in_string, mut escaped) = (false, false);
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == b'"' {
+                in_string = false;
+            }
+        } else if b == b'"' {
+            in_string = true;
+        } else if let Some(token) = TOKENS.iter().find(|t| bytes[i..].starts_with(t)) {
+            let out = out.get_or_insert_with(|| bytes[..i].to_vec());
+            out.push(b'"');
+            out.extend_from_slice(token);
+            out.push(b'"');
+            i += token.len();
+            continue;
+        }
+        if let Some(out) = &mut out {
+            out.push(b);
+        }
+        i += 1;
+    }
+    out.map_or(Cow::Borrowed(bytes), Cow::Owned)
+}
+
+/// Incremental splitter for json1 files.
+///
+/// `mas` writes one JSON object per record separated by `,\n`, sometimes wrapped in
+/// `[` ... `]`. The scanner tracks brace depth (ignoring braces inside strings) so
+/// records may span lines, and keeps a partially written trailing record until more
+/// bytes arrive.
+#[derive(Debug, Default)]
+pub struct RecordScanner {
+    offset: u64,
+    line: u64,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    current: Vec<u8>,
+    start: (u64, u64),
+    garbage: Option<(u64, u64, Vec<u8>)>,
+}
+
+impl RecordScanner {
+    pub fn new() -> Self {
+        Self {
+            line: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Bytes consumed so far, including any incomplete trailing record.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Byte offset just past the last complete record or separator.
+    pub fn committed_offset(&self) -> u64 {
+        self.offset - self.current.len() as u64
+    }
+
+    /// True while a record has been opened but not yet closed.
+    pub fn has_partial(&self) -> bool {
+        !self.current.is_empty()
+    }
+
+    /// Feed more bytes, appending complete records and stray data to `out`.
+    pub fn feed(&mut self, data: &[u8], out: &mut Vec<Result<RawRecord, RecordError>>) {
+        for &b in data {
+            if self.depth == 0 {
+                match b {
+                    b'{' => {
+                        self.flush_garbage(out);
+                        self.start = (self.offset, self.line);
+                        self.current.push(b);
+                        self.depth = 1;
+                    }
+                    b',' | b'[' | b']' | b' ' | b'\t' | b'\r' | b'\n' => self.flush_garbage(out),
+                    _ => {
+                        let (offset, line) = (self.offset, self.line);
+                        self.garbage.get_or_insert_with(|| (offset, line, Vec::new())).2.push(b);
+                    }
+                }
+            } else {
+                self.current.push(b);
+                if self.in_string {
+                    if self.escaped {
+                        self.escaped = false;
+                    } else if b == b'\\' {
+                        self.escaped = true;
+                    } else if b == b'"' {
+                        self.in_string = false;
+                    }
+                } else {
+                    match b {
+                        b'"' => self.in_string = true,
+                        b'{' | b'[' => self.depth += 1,
+                        b'}' | b']' => {
+                            self.depth -= 1;
+                            if self.depth == 0 {
+                                out.push(Ok(RawRecord {
+                                    offset: self.start.0,
+                                    line: self.start.1,
+                                    bytes: std::mem::take(&mut self.current),
+                                }));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            self.offset += 1;
+            if b == b'\n' {
+                self.line += 1;
+            }
+        }
+    }
+
+    /// Report whatever is left once the input is known to be complete.
+    pub fn finish(&mut self, out: &mut Vec<Result<RawRecord, RecordError>>) {
+        self.flush_garbage(out);
+        if !self.current.is_empty() {
+            out.push(Err(RecordError {
+                offset: self.start.0,
+                line: self.start.1,
+                message: "truncated record at end of file".to_string(),
+            }));
+            self.current.clear();
+            self.depth = 0;
+            self.in_string = false;
+            self.escaped = false;
+        }
+    }
+
+    fn flush_garbage(&mut self, out: &mut Vec<Result<RawRecord, RecordError>>) {
+        if let Some((offset, line, bytes)) = self.garbage.take() {
+            out.push(Err(RecordError {
+                offset,
+                line,
+                message: format!("unexpected data outside a record: {:?}", String::from_utf8_lossy(&bytes)),
+            }));
+        }
+    }
+}
+
+/// Streaming reader yielding one frame (or one malformed record) at a time.
+pub struct FrameReader<R: Read> {
+    reader: R,
+    scanner: RecordScanner,
+    pending: VecDeque<Result<RawRecord, RecordError>>,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            scanner: RecordScanner::new(),
+            pending: Default::default(),
+            buf: vec![0; 64 * 1024],
+            eof: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = Result<Frame, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(record.and_then(|r| r.parse()).map_err(ParseError::Malformed));
+            }
+            if self.eof {
+                return None;
+            }
+
+            let n = match self.reader.read(&mut self.buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.eof = true;
+                    return Some(Err(ParseError::Io(e)));
+                }
+            };
+            let mut out = Vec::new();
+            if n == 0 {
+                self.eof = true;
+                self.scanner.finish(&mut out);
+            } else {
+                self.scanner.feed(&self.buf[..n], &mut out);
+            }
+            self.pending.extend(out);
+        }
+    }
+}
+
+/// All frames of a file plus the records that had to be skipped.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Parsed {
+    pub frames: Vec<Frame>,
+    pub errors: Vec<RecordError>,
+}
+
+pub fn parse_reader<R: Read>(reader: R) -> io::Result<Parsed> {
+    let mut parsed = Parsed::default();
+    for item in FrameReader::new(reader) {
+        match item {
+            Ok(frame) => parsed.frames.push(frame),
+            Err(ParseError::Malformed(e)) => parsed.errors.push(e),
+            Err(ParseError::Io(e)) => return Err(e),
+        }
+    }
+    Ok(parsed)
+}
+
+pub fn load_trajectory<P: AsRef<Path>>(path: P) -> io::Result<Parsed> {
+    parse_reader(File::open(path)?)
+}
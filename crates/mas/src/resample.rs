@@ -0,0 +1,218 @@
+use std::f64::consts::{PI, TAU};
+
+use serde::Deserialize;
+
+use crate::frame::{Frame, Point, Signal, State};
+use crate::kinematics::{differentiate, DifferenceScheme};
+
+/// Times to resample a run at.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TimeGrid {
+    /// Explicit, ascending sample times.
+    Times { times: Vec<f64> },
+    /// Every `dt` from `start` to `end`, which default to the run's first and last time.
+    Uniform {
+        dt: f64,
+        #[serde(default)]
+        start: Option<f64>,
+        #[serde(default)]
+        end: Option<f64>,
+    },
+}
+
+// Refuse grids larger than this, usually a mistyped step
+pub const MAX_SAMPLES: usize = 10_000_000;
+
+impl TimeGrid {
+    /// Sample times for a run spanning `[first, last]`. An empty span gives no samples;
+    /// a non-positive or non-finite `dt`, or more than [`MAX_SAMPLES`] samples, is an error.
+    pub fn times(&self, first: f64, last: f64) -> Result<Vec<f64>, String> {
+        match *self {
+            TimeGrid::Times { ref times } => Ok(times.clone()),
+            TimeGrid::Uniform { dt, start, end } => {
+                if !(dt > 0.0 && dt.is_finite()) {
+                    return Err(format!("dt must be positive and finite, got {}", dt));
+                }
+                let start = start.unwrap_or(first);
+                let end = end.unwrap_or(last);
+                if !(start.is_finite() && end.is_finite()) {
+                    return Err(format!("The grid bounds must be finite, got {} to {}", start, end));
+                }
+                if end < start {
+                    return Ok(Vec::new());
+                }
+                // Tolerate rounding so that `end` itself is included when it lies on the grid
+                let steps = ((end - start) / dt + 1e-9).floor();
+                if steps >= MAX_SAMPLES as f64 {
+                    return Err(format!("A step of {} gives more than {} samples", dt, MAX_SAMPLES));
+                }
+                Ok((0..=steps as usize).map(|i| (start + i as f64 * dt).min(end)).collect())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Cubic Hermite with tangents from the central difference of the samples.
+    CubicHermite,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResampleOptions {
+    pub grid: TimeGrid,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+fn lerp(a: Point, b: Point, s: f64) -> Point {
+    [a[0] + s * (b[0] - a[0]), a[1] + s * (b[1] - a[1])]
+}
+
+fn hermite(p0: Point, m0: Point, p1: Point, m1: Point, h: f64, s: f64) -> Point {
+    let (s2, s3) = (s * s, s * s * s);
+    let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+    let h10 = s3 - 2.0 * s2 + s;
+    let h01 = -2.0 * s3 + 3.0 * s2;
+    let h11 = s3 - s2;
+    [0, 1].map(|c| h00 * p0[c] + h10 * h * m0[c] + h01 * p1[c] + h11 * h * m1[c])
+}
+
+/// Interpolates a rotation vector in polar form: the angle along the shorter arc and
+/// the magnitude linearly. Vectors too short to have a direction are interpolated linearly.
+fn slerp(a: Point, b: Point, s: f64) -> Point {
+    let (ra, rb) = (a[0].hypot(a[1]), b[0].hypot(b[1]));
+    if ra < 1e-12 || rb < 1e-12 {
+        return lerp(a, b, s);
+    }
+    let (ta, tb) = (a[1].atan2(a[0]), b[1].atan2(b[0]));
+    let delta = (tb - ta + PI).rem_euclid(TAU) - PI;
+    let (r, t) = (ra + s * (rb - ra), ta + s * delta);
+    [r * t.cos(), r * t.sin()]
+}
+
+/// One body's samples, with tangents when cubic interpolation is requested.
+struct Track {
+    positions: Vec<Point>,
+    tangents: Option<Vec<Point>>,
+}
+
+impl Track {
+    fn new(time: &[f64], positions: Vec<Point>, interpolation: Interpolation) -> Self {
+        let tangents = match interpolation {
+            Interpolation::Linear => None,
+            Interpolation::CubicHermite => {
+                let d = |c: usize| {
+                    let xs: Vec<f64> = positions.iter().map(|p| p[c]).collect();
+                    differentiate(time, &xs, DifferenceScheme::Central)
+                };
+                Some(d(0).into_iter().zip(d(1)).map(|(x, y)| [x, y]).collect())
+            }
+        };
+        Self { positions, tangents }
+    }
+
+    fn at(&self, i: usize, h: f64, s: f64) -> Point {
+        let (p0, p1) = (self.positions[i], self.positions[i + 1]);
+        match &self.tangents {
+            Some(m) if m[i].iter().chain(&m[i + 1]).all(|v| v.is_finite()) => hermite(p0, m[i], p1, m[i + 1], h, s),
+            _ => lerp(p0, p1, s),
+        }
+    }
+}
+
+/// Interpolates `frames` at `times`. Frames with a non-finite time are ignored, and of
+/// frames sharing a time only the last is used. Times outside the run are dropped.
+///
+/// Each resampled frame carries a single signal: the latest rotations interpolated
+/// between the neighbouring frames, with the distance terms of the nearer one.
+pub fn resample_at(frames: &[Frame], times: &[f64], interpolation: Interpolation) -> Vec<Frame> {
+    let mut samples: Vec<&Frame> = frames.iter().filter(|f| f.time.is_finite()).collect();
+    samples.sort_by(|a, b| a.time.total_cmp(&b.time));
+    // `dedup_by` keeps the first of a run, so walk backwards to keep the last
+    samples.reverse();
+    samples.dedup_by(|a, b| a.time == b.time);
+    samples.reverse();
+
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Vec::new();
+    };
+    let (first, last) = (first.time, last.time);
+
+    let t: Vec<f64> = samples.iter().map(|f| f.time).collect();
+    let agent_count = samples.iter().map(|f| f.agent_count()).max().unwrap_or(0);
+    let agents: Vec<Track> = (0..agent_count)
+        .map(|j| {
+            let positions = samples.iter().map(|f| f.state.agents.get(j).copied().unwrap_or([f64::NAN; 2])).collect();
+            Track::new(&t, positions, interpolation)
+        })
+        .collect();
+    let target = Track::new(&t, samples.iter().map(|f| f.state.target).collect(), interpolation);
+
+    times
+        .iter()
+        .filter(|&&time| time >= first && time <= last)
+        .map(|&time| {
+            if samples.len() == 1 {
+                return samples[0].clone();
+            }
+            // Segment `[t[i], t[i + 1]]` containing `time`
+            let i = t.partition_point(|&ti| ti <= time).clamp(1, t.len() - 1) - 1;
+            let (a, b) = (samples[i], samples[i + 1]);
+            let h = t[i + 1] - t[i];
+            let s = (time - t[i]) / h;
+
+            let count = a.agent_count().min(b.agent_count());
+            let state = State {
+                agents: agents[..count].iter().map(|track| track.at(i, h, s)).collect(),
+                target: target.at(i, h, s),
+            };
+
+            let nearer = if s < 0.5 { a } else { b };
+            let signals = match (a.latest_rotations(), b.latest_rotations()) {
+                (Some(ra), Some(rb)) => vec![Signal {
+                    distance: nearer.signals.last().map(|g| g.distance.clone()).unwrap_or_default(),
+                    rotations: ra.iter().zip(rb).map(|(&p, &q)| slerp(p, q, s)).collect(),
+                }],
+                _ => nearer.signals.last().cloned().into_iter().collect(),
+            };
+
+            Frame { time, state, signals }
+        })
+        .collect()
+}
+
+pub fn resample(frames: &[Frame], options: &ResampleOptions) -> Result<Vec<Frame>, String> {
+    let finite = || frames.iter().map(|f| f.time).filter(|t| t.is_finite());
+    let first = finite().fold(f64::INFINITY, f64::min);
+    let last = finite().fold(f64::NEG_INFINITY, f64::max);
+    if first > last {
+        return Ok(Vec::new());
+    }
+    Ok(resample_at(frames, &options.grid.times(first, last)?, options.interpolation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(dt: f64) -> TimeGrid {
+        TimeGrid::Uniform { dt, start: None, end: None }
+    }
+
+    #[test]
+    fn uniform_grid_includes_the_end() {
+        assert_eq!(uniform(0.5).times(0.0, 1.0).unwrap(), [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn bad_steps_are_rejected() {
+        for dt in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-13] {
+            assert!(uniform(dt).times(0.0, 10.0).is_err(), "dt = {}", dt);
+        }
+    }
+}
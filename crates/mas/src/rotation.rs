@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::f64::consts::{PI, TAU};
+
+use serde::{Deserialize, Serialize};
+
+use crate::frame::Frame;
+use crate::kinematics::{differentiate, DifferenceScheme};
+use crate::spacing::polar_angle;
+
+/// Remove the 2π jumps from a sequence of angles. `NaN` samples are kept and
+/// unwrapping continues from the last valid angle.
+pub fn unwrap_phase(angles: &[f64]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(angles.len());
+    let mut offset = 0.0;
+    let mut previous: Option<f64> = None;
+    for &a in angles {
+        if a.is_nan() {
+            out.push(f64::NAN);
+            continue;
+        }
+        if let Some(p) = previous {
+            let delta = a - p;
+            if delta > PI {
+                offset -= TAU * ((delta - PI) / TAU).ceil();
+            } else if delta < -PI {
+                offset += TAU * ((-delta - PI) / TAU).ceil();
+            }
+        }
+        previous = Some(a);
+        out.push(a + offset);
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RotationOptions {
+    pub scheme: DifferenceScheme,
+    /// Length of the windows (in simulation time) for the mean/variance statistics.
+    pub window: f64,
+    /// `|ω|` the formation must exceed on both sides for a sign change to count as a reversal.
+    pub reversal_threshold: f64,
+}
+
+/// Mean and variance of the formation's angular velocity over `[start, end)`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowStats {
+    pub start: f64,
+    pub end: f64,
+    pub mean: f64,
+    pub variance: f64,
+}
+
+/// The formation switched from rotating one way to the other.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reversal {
+    pub time: f64,
+    pub frame: usize,
+    /// True when the new direction is counter-clockwise.
+    pub to_ccw: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationAnalysis {
+    pub time: Vec<f64>,
+    /// Per agent, its unwrapped bearing around the target.
+    pub phase: Vec<Vec<f64>>,
+    /// Per agent, `dθ/dt` (positive is counter-clockwise).
+    pub angular_velocity: Vec<Vec<f64>>,
+    /// Mean angular velocity over the agents present in each frame.
+    pub mean_angular_velocity: Vec<f64>,
+    pub windows: Vec<WindowStats>,
+    pub reversals: Vec<Reversal>,
+}
+
+fn window_stats(time: &[f64], omega: &[f64], window: f64) -> Vec<WindowStats> {
+    let Some(&first) = time.first() else {
+        return Vec::new();
+    };
+    if window.is_nan() || window <= 0.0 {
+        return Vec::new();
+    }
+    // Beyond 2^52 windows the indices are no longer exact, and would overflow further out
+    let last = time.iter().copied().filter(|t| t.is_finite()).fold(first, f64::max);
+    let spans = (last - first) / window;
+    if spans.is_nan() || spans >= (1u64 << 52) as f64 {
+        return Vec::new();
+    }
+
+    // Window index -> (sum, sum of squares, count); empty windows are left out
+    let mut buckets: BTreeMap<i64, (f64, f64, usize)> = BTreeMap::new();
+    for (&t, &w) in time.iter().zip(omega) {
+        if w.is_nan() {
+            continue;
+        }
+        let bucket = buckets.entry(((t - first) / window).floor() as i64).or_default();
+        bucket.0 += w;
+        bucket.1 += w * w;
+        bucket.2 += 1;
+    }
+
+    buckets
+        .into_iter()
+        .map(|(k, (sum, sum_sq, n))| {
+            let mean = sum / n as f64;
+            WindowStats {
+                start: first + k as f64 * window,
+                end: first + (k + 1) as f64 * window,
+                mean,
+                variance: (sum_sq / n as f64 - mean * mean).max(0.0),
+            }
+        })
+        .collect()
+}
+
+fn reversals(time: &[f64], omega: &[f64], threshold: f64) -> Vec<Reversal> {
+    let mut found = Vec::new();
+    // Direction of the last sample that was clearly rotating
+    let mut direction: Option<bool> = None;
+    for (k, (&t, &w)) in time.iter().zip(omega).enumerate() {
+        if w.is_nan() || w.abs() <= threshold {
+            continue;
+        }
+        let ccw = w > 0.0;
+        if direction.is_some_and(|d| d != ccw) {
+            found.push(Reversal { time: t, frame: k, to_ccw: ccw });
+        }
+        direction = Some(ccw);
+    }
+    found
+}
+
+pub fn formation_rotation(frames: &[Frame], options: RotationOptions) -> RotationAnalysis {
+    let agent_count = frames.iter().map(Frame::agent_count).max().unwrap_or(0);
+    let time: Vec<f64> = frames.iter().map(|f| f.time).collect();
+
+    let phase: Vec<Vec<f64>> = (0..agent_count)
+        .map(|j| {
+            let bearings: Vec<f64> = frames
+                .iter()
+                .map(|f| {
+                    f.state
+                        .agents
+                        .get(j)
+                        .and_then(|&p| polar_angle(p, f.state.target))
+                        .unwrap_or(f64::NAN)
+                })
+                .collect();
+            unwrap_phase(&bearings)
+        })
+        .collect();
+    let angular_velocity: Vec<Vec<f64>> = phase.iter().map(|p| differentiate(&time, p, options.scheme)).collect();
+
+    let mean_angular_velocity: Vec<f64> = (0..frames.len())
+        .map(|k| {
+            let present: Vec<f64> = angular_velocity.iter().map(|w| w[k]).filter(|w| !w.is_nan()).collect();
+            present.iter().sum::<f64>() / present.len() as f64
+        })
+        .collect();
+
+    RotationAnalysis {
+        windows: window_stats(&time, &mean_angular_velocity, options.window),
+        reversals: reversals(&time, &mean_angular_velocity, options.reversal_threshold),
+        time,
+        phase,
+        angular_velocity,
+        mean_angular_velocity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Point, State};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn unwrap_removes_jumps_and_skips_nan() {
+        let unwrapped = unwrap_phase(&[6.0, 0.2, f64::NAN, 0.5, 6.1, 5.0]);
+        let expected = [6.0, 0.2 + TAU, f64::NAN, 0.5 + TAU, 6.1, 5.0];
+        for (a, b) in unwrapped.iter().zip(expected) {
+            assert!(close(*a, b) || (a.is_nan() && b.is_nan()), "{:?}", unwrapped);
+        }
+    }
+
+    fn orbit(omega: impl Fn(f64) -> f64, steps: usize) -> Vec<Frame> {
+        // Integrate the phase so the angular velocity can change sign
+        let dt = 0.01;
+        let mut theta = 0.0;
+        (0..steps)
+            .map(|k| {
+                let t = k as f64 * dt;
+                let agents: Vec<Point> = (0..3)
+                    .map(|j| {
+                        let a = theta + j as f64 * TAU / 3.0;
+                        [1.0 + 2.0 * a.cos(), -1.0 + 2.0 * a.sin()]
+                    })
+                    .collect();
+                theta += omega(t) * dt;
+                Frame {
+                    time: t,
+                    state: State { agents, target: [1.0, -1.0] },
+                    signals: Vec::new(),
+                }
+            })
+            .collect()
+    }
+
+    fn options(window: f64) -> RotationOptions {
+        RotationOptions {
+            scheme: DifferenceScheme::Central,
+            window,
+            reversal_threshold: 0.5,
+        }
+    }
+
+    #[test]
+    fn steady_rotation_has_constant_angular_velocity() {
+        let analysis = formation_rotation(&orbit(|_| 1.5, 500), options(1.0));
+        assert!(analysis.mean_angular_velocity.iter().all(|&w| (w - 1.5).abs() < 1e-6));
+        // Several full turns, without jumps
+        assert!(analysis.phase[0][499] > TAU);
+        assert_eq!(analysis.windows.len(), 5);
+        assert!(analysis.windows.iter().all(|w| (w.mean - 1.5).abs() < 1e-6 && w.variance < 1e-9));
+        assert!(analysis.reversals.is_empty());
+    }
+
+    #[test]
+    fn reversal_is_found_where_the_direction_changes() {
+        let analysis = formation_rotation(&orbit(|t| if t < 2.0 { 2.0 } else { -2.0 }, 400), options(1.0));
+        assert_eq!(analysis.reversals.len(), 1);
+        let reversal = &analysis.reversals[0];
+        assert!(!reversal.to_ccw);
+        assert!((reversal.time - 2.0).abs() < 0.03, "{}", reversal.time);
+    }
+
+    #[test]
+    fn tiny_windows_do_not_overflow() {
+        assert!(formation_rotation(&orbit(|_| 1.0, 10), options(1e-300)).windows.is_empty());
+        assert_eq!(formation_rotation(&orbit(|_| 1.0, 10), options(0.01)).windows.len(), 10);
+        assert!(formation_rotation(&orbit(|_| 1.0, 10), options(0.0)).windows.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use std::f64::consts::TAU;
+
+use serde::Serialize;
+
+use crate::frame::{Frame, Point};
+use crate::metrics::convergence_time;
+
+/// Polar angle of `p` around `center`, in `[0, 2π)`. `None` if the two coincide.
+pub fn polar_angle(p: Point, center: Point) -> Option<f64> {
+    let (dx, dy) = (p[0] - center[0], p[1] - center[1]);
+    if dx == 0.0 && dy == 0.0 {
+        return None;
+    }
+    Some(dy.atan2(dx).rem_euclid(TAU))
+}
+
+/// Gaps between consecutive angles going counter-clockwise, starting from the
+/// smallest angle. The gaps always sum to 2π.
+pub fn angle_gaps(angles: &[f64]) -> Vec<f64> {
+    let mut sorted = angles.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let n = sorted.len();
+    (0..n)
+        .map(|k| {
+            if n == 1 {
+                TAU
+            } else if k + 1 < n {
+                sorted[k + 1] - sorted[k]
+            } else {
+                sorted[0] + TAU - sorted[k]
+            }
+        })
+        .collect()
+}
+
+/// 1 for perfectly even spacing, 0 when all agents share one bearing.
+///
+/// Based on the total absolute deviation of the gaps from 2π/N, normalised by its
+/// largest possible value 2·2π·(1 - 1/N). `NaN` for fewer than two agents.
+pub fn uniformity_index(gaps: &[f64]) -> f64 {
+    let n = gaps.len();
+    if n < 2 {
+        return f64::NAN;
+    }
+    let ideal = TAU / n as f64;
+    let deviation: f64 = gaps.iter().map(|g| (g - ideal).abs()).sum();
+    1.0 - deviation / (2.0 * TAU * (1.0 - 1.0 / n as f64))
+}
+
+/// How evenly the agents are spread around the target over a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpacingAnalysis {
+    pub time: Vec<f64>,
+    /// Per agent, its bearing from the target in `[0, 2π)` per frame (`NaN` if undefined).
+    pub angles: Vec<Vec<f64>>,
+    /// Per frame, the neighbour gaps in counter-clockwise order.
+    pub gaps: Vec<Vec<f64>>,
+    /// Per frame, the largest `|gap - 2π/N|`.
+    pub max_gap_deviation: Vec<f64>,
+    pub uniformity: Vec<f64>,
+    /// First time after which `1 - uniformity` stays below the requested tolerance.
+    pub settling_time: Option<f64>,
+}
+
+pub fn angular_spacing(frames: &[Frame], tolerance: f64) -> SpacingAnalysis {
+    let agent_count = frames.iter().map(Frame::agent_count).max().unwrap_or(0);
+    let time: Vec<f64> = frames.iter().map(|f| f.time).collect();
+    let mut angles = vec![Vec::with_capacity(frames.len()); agent_count];
+    let mut gaps = Vec::with_capacity(frames.len());
+    let mut max_gap_deviation = Vec::with_capacity(frames.len());
+    let mut uniformity = Vec::with_capacity(frames.len());
+
+    for frame in frames {
+        let target = frame.state.target;
+        let mut present = Vec::with_capacity(agent_count);
+        for (j, series) in angles.iter_mut().enumerate() {
+            let angle = frame.state.agents.get(j).and_then(|&p| polar_angle(p, target));
+            series.push(angle.unwrap_or(f64::NAN));
+            present.extend(angle);
+        }
+
+        let frame_gaps = angle_gaps(&present);
+        let ideal = TAU / frame_gaps.len().max(1) as f64;
+        max_gap_deviation.push(frame_gaps.iter().map(|g| (g - ideal).abs()).fold(f64::NAN, f64::max));
+        uniformity.push(uniformity_index(&frame_gaps));
+        gaps.push(frame_gaps);
+    }
+
+    let disorder: Vec<f64> = uniformity.iter().map(|u| 1.0 - u).collect();
+    let settling_time = convergence_time(&time, &disorder, tolerance);
+
+    SpacingAnalysis {
+        time,
+        angles,
+        gaps,
+        max_gap_deviation,
+        uniformity,
+        settling_time,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    use super::*;
+    use crate::frame::State;
+
+    fn frame(time: f64, agents: &[Point]) -> Frame {
+        Frame {
+            time,
+            state: State { agents: agents.to_vec(), target: [0.0, 0.0] },
+            signals: Vec::new(),
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn polar_angles_are_in_zero_to_tau() {
+        assert!(close(polar_angle([0.0, 1.0], [0.0, 0.0]).unwrap(), FRAC_PI_2));
+        assert!(close(polar_angle([1.0, -1.0], [1.0, 0.0]).unwrap(), 3.0 * FRAC_PI_2));
+        assert_eq!(polar_angle([2.0, 3.0], [2.0, 3.0]), None);
+    }
+
+    #[test]
+    fn gaps_wrap_around_and_sum_to_tau() {
+        let gaps = angle_gaps(&[3.0 * FRAC_PI_2, 0.5, PI]);
+        assert!(close(gaps[0], PI - 0.5));
+        assert!(close(gaps[1], FRAC_PI_2));
+        assert!(close(gaps[2], 0.5 + FRAC_PI_2));
+        assert!(close(gaps.iter().sum(), TAU));
+        assert_eq!(angle_gaps(&[1.0]), vec![TAU]);
+    }
+
+    #[test]
+    fn uniformity_ranges_from_clustered_to_even() {
+        assert!(close(uniformity_index(&[FRAC_PI_2; 4]), 1.0));
+        assert!(close(uniformity_index(&[0.0, 0.0, 0.0, TAU]), 0.0));
+        assert!(uniformity_index(&[TAU]).is_nan());
+    }
+
+    #[test]
+    fn settling_time_follows_the_spread() {
+        let frames = [
+            frame(0.0, &[[1.0, 0.0], [1.0, 0.1], [1.0, -0.1]]),
+            frame(1.0, &[[1.0, 0.0], [-0.5, 0.75_f64.sqrt()], [-0.5, -(0.75_f64.sqrt())]]),
+            frame(2.0, &[[2.0, 0.0], [-1.0, 3.0_f64.sqrt()], [-1.0, -(3.0_f64.sqrt())]]),
+        ];
+        let spacing = angular_spacing(&frames, 1e-9);
+
+        assert!(spacing.uniformity[0] < 0.2);
+        assert!(close(spacing.uniformity[1], 1.0) && close(spacing.uniformity[2], 1.0));
+        assert!(close(spacing.max_gap_deviation[2], 0.0));
+        assert!(close(spacing.angles[1][1], 2.0 * PI / 3.0));
+        assert_eq!(spacing.settling_time, Some(1.0));
+    }
+}
@@ -0,0 +1,369 @@
+use std::f64::consts::{PI, TAU};
+
+use serde::{Deserialize, Serialize};
+
+use crate::frame::Frame;
+use crate::metrics::distance;
+use crate::resample::{resample_at, Interpolation, TimeGrid};
+use crate::rotation::unwrap_phase;
+use crate::spacing::polar_angle;
+
+fn default_segment() -> usize {
+    256
+}
+
+fn default_peaks() -> usize {
+    3
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SpectrumOptions {
+    /// Step the run is resampled to before the analysis.
+    pub dt: f64,
+    pub desired_radius: f64,
+    /// Samples per Welch segment, rounded up to a power of two. Segments overlap by half.
+    #[serde(default = "default_segment")]
+    pub segment: usize,
+    /// How many dominant frequencies to report per series.
+    #[serde(default = "default_peaks")]
+    pub peaks: usize,
+}
+
+/// In-place radix-2 FFT. The length must be a power of two.
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let step = -TAU / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (step * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let vr = re[b] * cos - im[b] * sin;
+                let vi = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - vr;
+                im[b] = im[a] - vi;
+                re[a] += vr;
+                im[a] += vi;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Removes the least-squares line from `x`.
+fn detrend(x: &mut [f64]) {
+    let n = x.len() as f64;
+    let mean_i = (n - 1.0) / 2.0;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (i, &v) in x.iter().enumerate() {
+        let d = i as f64 - mean_i;
+        sxy += d * (v - mean_x);
+        sxx += d * d;
+    }
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    for (i, v) in x.iter_mut().enumerate() {
+        *v -= mean_x + slope * (i as f64 - mean_i);
+    }
+}
+
+/// Samples per segment and FFT length for a series of `n` samples. The segment is
+/// clamped to the series before rounding, so a huge one cannot overflow.
+fn segment_size(n: usize, segment: usize) -> (usize, usize) {
+    let len = segment.clamp(2, n.max(2)).next_power_of_two().min(n);
+    (len, len.next_power_of_two())
+}
+
+/// Frequencies of the bins [`welch`] returns for a series of `n` samples.
+pub fn welch_frequencies(n: usize, fs: f64, segment: usize) -> Vec<f64> {
+    let (len, nfft) = segment_size(n, segment);
+    if len < 2 {
+        return Vec::new();
+    }
+    (0..=nfft / 2).map(|k| k as f64 * fs / nfft as f64).collect()
+}
+
+/// One-sided power spectral density by Welch's method: linearly detrended,
+/// Hann-windowed segments of `segment` samples with 50% overlap, averaged.
+pub fn welch(x: &[f64], fs: f64, segment: usize) -> Vec<f64> {
+    let (len, nfft) = segment_size(x.len(), segment);
+    if len < 2 {
+        return Vec::new();
+    }
+    let window: Vec<f64> = (0..len).map(|i| 0.5 - 0.5 * (TAU * i as f64 / len as f64).cos()).collect();
+    let scale = fs * window.iter().map(|w| w * w).sum::<f64>();
+
+    let mut psd = vec![0.0; nfft / 2 + 1];
+    let mut segments = 0;
+    let hop = (len / 2).max(1);
+    let mut start = 0;
+    while start + len <= x.len() {
+        let mut re = x[start..start + len].to_vec();
+        detrend(&mut re);
+        for (v, w) in re.iter_mut().zip(&window) {
+            *v *= w;
+        }
+        re.resize(nfft, 0.0);
+        let mut im = vec![0.0; nfft];
+        fft(&mut re, &mut im);
+        for (k, p) in psd.iter_mut().enumerate() {
+            *p += re[k] * re[k] + im[k] * im[k];
+        }
+        segments += 1;
+        start += hop;
+    }
+
+    for (k, p) in psd.iter_mut().enumerate() {
+        // Fold the negative frequencies in, except for DC and Nyquist
+        let fold = if k == 0 || k == nfft / 2 { 1.0 } else { 2.0 };
+        *p *= fold / (scale * segments as f64);
+    }
+    psd
+}
+
+/// A local maximum of a spectrum.
+#[derive(Debug, Clone, Serialize)]
+pub struct Peak {
+    pub frequency: f64,
+    pub power: f64,
+    /// Damping ratio from the half-power bandwidth, `(f₂ - f₁) / 2f₀`. Limited by the
+    /// frequency resolution, so lightly damped peaks read high.
+    pub damping: Option<f64>,
+}
+
+fn half_power_damping(frequency: &[f64], psd: &[f64], k: usize) -> Option<f64> {
+    let half = psd[k] / 2.0;
+    let crossing = |i: usize, j: usize| {
+        let s = (psd[i] - half) / (psd[i] - psd[j]);
+        frequency[i] + s * (frequency[j] - frequency[i])
+    };
+    let below = (1..k).rev().find(|&i| psd[i] < half)?;
+    let above = (k + 1..psd.len()).find(|&i| psd[i] < half)?;
+    let (f1, f2) = (crossing(below + 1, below), crossing(above - 1, above));
+    Some((f2 - f1) / (2.0 * frequency[k]))
+}
+
+/// The `count` strongest local maxima, DC excluded.
+fn dominant_peaks(frequency: &[f64], psd: &[f64], count: usize) -> Vec<Peak> {
+    let mut maxima: Vec<usize> = (1..psd.len())
+        .filter(|&k| psd[k] > psd[k - 1] && psd.get(k + 1).is_none_or(|&next| psd[k] >= next))
+        .collect();
+    maxima.sort_by(|&a, &b| psd[b].total_cmp(&psd[a]));
+    maxima
+        .into_iter()
+        .take(count)
+        .map(|k| Peak {
+            frequency: frequency[k],
+            power: psd[k],
+            damping: half_power_damping(frequency, psd, k),
+        })
+        .collect()
+}
+
+/// Damping ratio from the logarithmic decrement of successive maxima of the
+/// detrended series. Negative when the oscillation grows.
+fn decrement_damping(x: &[f64]) -> Option<f64> {
+    let mut y = x.to_vec();
+    detrend(&mut y);
+    let maxima: Vec<f64> = (1..y.len().saturating_sub(1))
+        .filter(|&i| y[i] > 0.0 && y[i] > y[i - 1] && y[i] >= y[i + 1])
+        .map(|i| y[i].ln())
+        .collect();
+    if maxima.len() < 3 {
+        return None;
+    }
+    // Least-squares slope of ln(amplitude) against the cycle number
+    let n = maxima.len() as f64;
+    let mean_k = (n - 1.0) / 2.0;
+    let mean_a = maxima.iter().sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (k, a) in maxima.iter().enumerate() {
+        sxy += (k as f64 - mean_k) * (a - mean_a);
+        sxx += (k as f64 - mean_k).powi(2);
+    }
+    let delta = -sxy / sxx;
+    Some(delta / (4.0 * PI * PI + delta * delta).sqrt())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeriesSpectrum {
+    pub psd: Vec<f64>,
+    pub peaks: Vec<Peak>,
+    /// Time-domain damping ratio from the logarithmic decrement.
+    pub damping: Option<f64>,
+}
+
+impl SeriesSpectrum {
+    fn of(x: &[f64], frequency: &[f64], options: &SpectrumOptions) -> Self {
+        let psd = welch(x, 1.0 / options.dt, options.segment);
+        Self {
+            peaks: dominant_peaks(frequency, &psd, options.peaks),
+            damping: decrement_damping(x),
+            psd,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentSpectrum {
+    pub radius_error: SeriesSpectrum,
+    /// Of the unwrapped bearing around the target; its mean rotation is detrended away.
+    pub angle: SeriesSpectrum,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectralAnalysis {
+    /// Frequencies of the PSD bins, in cycles per unit of simulation time.
+    pub frequency: Vec<f64>,
+    pub agents: Vec<AgentSpectrum>,
+}
+
+/// Samples missing from a series (agent absent) are filled with the series mean.
+fn fill_gaps(mut x: Vec<f64>) -> Vec<f64> {
+    let valid: Vec<f64> = x.iter().copied().filter(|v| v.is_finite()).collect();
+    let mean = if valid.is_empty() { 0.0 } else { valid.iter().sum::<f64>() / valid.len() as f64 };
+    for v in &mut x {
+        if !v.is_finite() {
+            *v = mean;
+        }
+    }
+    x
+}
+
+pub fn spectral_analysis(frames: &[Frame], options: SpectrumOptions) -> Result<SpectralAnalysis, String> {
+    let finite = || frames.iter().map(|f| f.time).filter(|t| t.is_finite());
+    let first = finite().fold(f64::INFINITY, f64::min);
+    let last = finite().fold(f64::NEG_INFINITY, f64::max);
+    let grid = if first <= last {
+        TimeGrid::Uniform {
+            dt: options.dt,
+            start: None,
+            end: None,
+        }
+        .times(first, last)?
+    } else {
+        Vec::new()
+    };
+    let resampled = resample_at(frames, &grid, Interpolation::Linear);
+
+    let frequency = welch_frequencies(resampled.len(), 1.0 / options.dt, options.segment);
+    let agent_count = resampled.iter().map(Frame::agent_count).max().unwrap_or(0);
+    let agents = (0..agent_count)
+        .map(|j| {
+            let at = |f: &Frame| f.state.agents.get(j).map(|&p| (p, f.state.target));
+            let radius_error: Vec<f64> = resampled
+                .iter()
+                .map(|f| at(f).map_or(f64::NAN, |(p, t)| distance(p, t) - options.desired_radius))
+                .collect();
+            let bearing: Vec<f64> = resampled
+                .iter()
+                .map(|f| at(f).and_then(|(p, t)| polar_angle(p, t)).unwrap_or(f64::NAN))
+                .collect();
+            AgentSpectrum {
+                radius_error: SeriesSpectrum::of(&fill_gaps(radius_error), &frequency, &options),
+                angle: SeriesSpectrum::of(&fill_gaps(unwrap_phase(&bearing)), &frequency, &options),
+            }
+        })
+        .collect();
+
+    Ok(SpectralAnalysis { frequency, agents })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Point, State};
+
+    #[test]
+    fn fft_of_a_cosine_has_two_bins() {
+        let n = 16;
+        let mut re: Vec<f64> = (0..n).map(|i| (TAU * 3.0 * i as f64 / n as f64).cos()).collect();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+        for k in 0..n {
+            let magnitude = re[k].hypot(im[k]);
+            let expected = if k == 3 || k == n - 3 { n as f64 / 2.0 } else { 0.0 };
+            assert!((magnitude - expected).abs() < 1e-9, "bin {}: {}", k, magnitude);
+        }
+    }
+
+    #[test]
+    fn segment_is_clamped_to_the_series() {
+        assert_eq!(segment_size(1000, 256), (256, 256));
+        assert_eq!(segment_size(1000, 300), (512, 512));
+        assert_eq!(segment_size(400, 300), (400, 512));
+        assert_eq!(segment_size(100, usize::MAX), (100, 128));
+        assert_eq!(segment_size(1, 0), (1, 1));
+        assert!(welch(&[1.0], 1.0, usize::MAX).is_empty());
+        assert_eq!(welch_frequencies(1000, 10.0, usize::MAX).len(), 513);
+    }
+
+    #[test]
+    fn welch_peak_is_at_the_signal_frequency() {
+        let (fs, f0) = (20.0, 1.5);
+        let x: Vec<f64> = (0..2048).map(|i| 0.3 * i as f64 / fs + (TAU * f0 * i as f64 / fs).sin()).collect();
+        let psd = welch(&x, fs, 256);
+        let frequency = welch_frequencies(x.len(), fs, 256);
+        assert_eq!(psd.len(), frequency.len());
+
+        let peaks = dominant_peaks(&frequency, &psd, 1);
+        assert!((peaks[0].frequency - f0).abs() <= fs / 256.0, "{}", peaks[0].frequency);
+        // Parseval: the density integrates to the variance of the sine
+        let power: f64 = psd.iter().sum::<f64>() * fs / 256.0;
+        assert!((power - 0.5).abs() < 0.05, "{}", power);
+    }
+
+    #[test]
+    fn decaying_oscillation_reports_its_damping() {
+        let zeta: f64 = 0.05;
+        let omega = TAU * 0.5;
+        let x: Vec<f64> = (0..4000)
+            .map(|i| {
+                let t = i as f64 * 0.01;
+                (-zeta * omega * t).exp() * (omega * (1.0 - zeta * zeta).sqrt() * t).cos()
+            })
+            .collect();
+        let damping = decrement_damping(&x).unwrap();
+        assert!((damping - zeta).abs() < 0.01, "{}", damping);
+        assert!(decrement_damping(&[0.0; 10]).is_none());
+    }
+
+    #[test]
+    fn radius_oscillation_shows_in_the_agent_spectrum() {
+        let frames: Vec<Frame> = (0..1000)
+            .map(|i| {
+                let t = i as f64 * 0.05;
+                let r = 2.0 + 0.2 * (TAU * 0.8 * t).sin();
+                let agent: Point = [r * (0.3 * t).cos(), r * (0.3 * t).sin()];
+                Frame {
+                    time: t,
+                    state: State { agents: vec![agent], target: [0.0, 0.0] },
+                    signals: Vec::new(),
+                }
+            })
+            .collect();
+        let options = SpectrumOptions { dt: 0.05, desired_radius: 2.0, segment: 256, peaks: 2 };
+        let analysis = spectral_analysis(&frames, options).unwrap();
+        let peak = &analysis.agents[0].radius_error.peaks[0];
+        assert!((peak.frequency - 0.8).abs() <= 20.0 / 256.0, "{}", peak.frequency);
+        assert!(spectral_analysis(&frames, SpectrumOptions { dt: 0.0, ..options }).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use serde::Serialize;
+
+use crate::frame::Frame;
+use crate::hull::containment;
+
+/// Headline numbers for one run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub frames: usize,
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+    pub agent_count: usize,
+    /// Fraction of frames with the target strictly inside the agents' hull.
+    pub containment_ratio: f64,
+    /// First time the target is contained.
+    pub first_containment: Option<f64>,
+    /// Number of times the target leaves the hull after having been contained.
+    pub escapes: usize,
+    /// Whether the target is contained in the final frame.
+    pub contained_at_end: bool,
+}
+
+pub fn summarize(frames: &[Frame]) -> RunSummary {
+    let c = containment(frames);
+    let first_containment = c.intervals.iter().find(|i| i.contained).map(|i| i.start);
+    let escapes = c
+        .intervals
+        .windows(2)
+        .filter(|w| w[0].contained && !w[1].contained)
+        .count();
+
+    RunSummary {
+        frames: frames.len(),
+        start_time: frames.first().map(|f| f.time),
+        end_time: frames.last().map(|f| f.time),
+        agent_count: frames.iter().map(Frame::agent_count).max().unwrap_or(0),
+        containment_ratio: c.ratio,
+        first_containment,
+        escapes,
+        contained_at_end: c.contained.last().copied().unwrap_or(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Point, State};
+
+    fn frame(time: f64, agents: &[Point], target: Point) -> Frame {
+        Frame {
+            time,
+            state: State { agents: agents.to_vec(), target },
+            signals: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn summary_counts_escapes() {
+        let square = [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+        let targets = [[3.0, 3.0], [1.0, 1.0], [3.0, 1.0], [1.0, 1.0], [1.0, 1.0]];
+        let frames: Vec<Frame> = targets.iter().enumerate().map(|(i, &t)| frame(i as f64, &square, t)).collect();
+        let summary = summarize(&frames);
+
+        assert_eq!((summary.frames, summary.agent_count), (5, 4));
+        assert_eq!((summary.start_time, summary.end_time), (Some(0.0), Some(4.0)));
+        assert_eq!(summary.first_containment, Some(1.0));
+        assert_eq!(summary.escapes, 1);
+        assert!(summary.contained_at_end);
+        assert_eq!(summary.containment_ratio, 0.6);
+    }
+}
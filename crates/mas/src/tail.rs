@@ -0,0 +1,188 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::frame::Frame;
+use crate::parser::{RecordError, RecordScanner};
+
+/// Frames appended since the previous poll.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TailBatch {
+    /// Index of `frames[0]` within the whole file.
+    pub first_index: usize,
+    pub frames: Vec<Frame>,
+    pub errors: Vec<RecordError>,
+    /// The file was truncated and is being read again from the start.
+    pub restarted: bool,
+}
+
+/// Follows a json1 file that is still being written.
+///
+/// Each [`poll`](TailFollower::poll) reads whatever was appended since the previous
+/// call. A record that is only half written stays buffered in the scanner until the
+/// rest of it arrives. If the file shrinks (the run was restarted and the file
+/// truncated) the follower starts over from the beginning.
+#[derive(Debug)]
+pub struct TailFollower {
+    path: PathBuf,
+    file: Option<File>,
+    scanner: RecordScanner,
+    frames_seen: usize,
+}
+
+impl TailFollower {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            file: None,
+            scanner: RecordScanner::new(),
+            frames_seen: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of frames returned so far; the index of the next frame.
+    pub fn frames_seen(&self) -> usize {
+        self.frames_seen
+    }
+
+    /// True if the last poll ended in the middle of a record.
+    pub fn has_partial(&self) -> bool {
+        self.scanner.has_partial()
+    }
+
+    /// Read newly appended data. A missing file is not an error: the simulation may
+    /// not have created it yet.
+    pub fn poll(&mut self) -> io::Result<TailBatch> {
+        let mut batch = TailBatch {
+            first_index: self.frames_seen,
+            ..Default::default()
+        };
+
+        if self.file.is_none() {
+            match File::open(&self.path) {
+                Ok(file) => self.file = Some(file),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(batch),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut len = std::fs::metadata(&self.path)?.len();
+        if len < self.scanner.offset() {
+            self.reset();
+            self.file = Some(File::open(&self.path)?);
+            len = std::fs::metadata(&self.path)?.len();
+            batch.first_index = 0;
+            batch.restarted = true;
+        }
+
+        let file = self.file.as_mut().expect("file opened above");
+        file.seek(SeekFrom::Start(self.scanner.offset()))?;
+
+        let mut data = Vec::new();
+        file.take(len - self.scanner.offset()).read_to_end(&mut data)?;
+
+        let mut out = Vec::new();
+        self.scanner.feed(&data, &mut out);
+        for record in out {
+            match record.and_then(|r| r.parse()) {
+                Ok(frame) => batch.frames.push(frame),
+                Err(e) => batch.errors.push(e),
+            }
+        }
+        self.frames_seen += batch.frames.len();
+        Ok(batch)
+    }
+
+    /// Report a trailing partial record once the writer is known to be done.
+    pub fn finish(&mut self) -> Vec<RecordError> {
+        let mut out = Vec::new();
+        self.scanner.finish(&mut out);
+        out.into_iter().filter_map(Result::err).collect()
+    }
+
+    fn reset(&mut self) {
+        self.file = None;
+        self.scanner = RecordScanner::new();
+        self.frames_seen = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    use super::*;
+
+    fn record(time: f64) -> String {
+        format!("{{\"time\": {:?}, \"state\": {{\"agents\": [[0.0, 0.0]], \"target\": [1.0, 1.0]}}}},\n", time)
+    }
+
+    fn times(batch: &TailBatch) -> Vec<f64> {
+        batch.frames.iter().map(|f| f.time).collect()
+    }
+
+    /// Where a test's `realtime.json1` goes, in a directory of its own that is otherwise empty.
+    fn fresh_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mas-tail-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("realtime.json1")
+    }
+
+    fn append(path: &Path, data: &str) {
+        OpenOptions::new().create(true).append(true).open(path).unwrap().write_all(data.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn follows_appends_and_waits_for_partial_records() {
+        let path = fresh_path("follow");
+        let mut follower = TailFollower::new(&path);
+
+        // Not created yet
+        assert!(follower.poll().unwrap().frames.is_empty());
+
+        let second = record(0.1);
+        let (head, rest) = second.split_at(20);
+        append(&path, &(record(0.0) + head));
+        let batch = follower.poll().unwrap();
+        assert_eq!((times(&batch), batch.first_index), (vec![0.0], 0));
+        assert!(follower.has_partial());
+
+        append(&path, &(rest.to_string() + "oops,\n" + &record(0.2)));
+        let batch = follower.poll().unwrap();
+        assert_eq!((times(&batch), batch.first_index), (vec![0.1, 0.2], 1));
+        assert_eq!(batch.errors.len(), 1);
+        assert!(!follower.has_partial() && follower.poll().unwrap().frames.is_empty());
+        assert_eq!(follower.frames_seen(), 3);
+    }
+
+    #[test]
+    fn truncated_file_restarts_from_the_beginning() {
+        let path = fresh_path("restart");
+        fs::write(&path, record(0.0) + &record(0.1) + &record(0.2)).unwrap();
+        let mut follower = TailFollower::new(&path);
+        assert_eq!(follower.poll().unwrap().frames.len(), 3);
+
+        fs::write(&path, record(5.0)).unwrap();
+        let batch = follower.poll().unwrap();
+        assert!(batch.restarted);
+        assert_eq!((times(&batch), batch.first_index), (vec![5.0], 0));
+        assert_eq!(follower.frames_seen(), 1);
+    }
+
+    #[test]
+    fn finish_reports_a_record_left_half_written() {
+        let path = fresh_path("finish");
+        fs::write(&path, record(0.0) + "{\"time\": 0.1, \"state\"").unwrap();
+        let mut follower = TailFollower::new(&path);
+        assert_eq!(follower.poll().unwrap().frames.len(), 1);
+        assert_eq!(follower.finish().len(), 1);
+    }
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{Frame, Point};
+use crate::metrics::distance;
+use crate::parser::RecordError;
+
+/// Bounds a physically plausible run must respect.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ValidationOptions {
+    /// Fastest an agent may move between two frames.
+    pub max_speed: f64,
+    /// Fastest the target may move; defaults to `max_speed`.
+    #[serde(default)]
+    pub max_target_speed: Option<f64>,
+}
+
+/// A body of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "body", rename_all = "snake_case")]
+pub enum Body {
+    Agent { agent: usize },
+    Target,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IssueKind {
+    /// A body covered `distance` in `dt`, faster than allowed.
+    Teleport {
+        #[serde(flatten)]
+        body: Body,
+        from: Point,
+        to: Point,
+        distance: f64,
+        dt: f64,
+        speed: f64,
+    },
+    /// `time` is smaller than in the previous frame.
+    TimeReversed { previous: f64 },
+    /// `time` is equal to the previous frame's.
+    TimeRepeated,
+    /// The number of agents differs from the previous frame.
+    AgentCountChanged { from: usize, to: usize },
+    /// The frame's `time` is `NaN` or infinite.
+    NonFiniteTime,
+    /// A position has a `NaN` or infinite coordinate.
+    NonFinitePosition {
+        #[serde(flatten)]
+        body: Body,
+    },
+    /// A record of the file could not be parsed, so it is missing from the frames.
+    RejectedRecord { offset: u64, line: u64, message: String },
+}
+
+/// One problem, located at the frame where it shows up.
+///
+/// `frame` and `time` are `None` for a rejected record, which never became a frame.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub frame: Option<usize>,
+    pub time: Option<f64>,
+    #[serde(flatten)]
+    pub kind: IssueKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub frames: usize,
+    /// True when no issue was found.
+    pub valid: bool,
+    pub issues: Vec<Issue>,
+    /// Indices of the frames with at least one issue, ascending.
+    pub flagged_frames: Vec<usize>,
+}
+
+fn is_finite(p: Point) -> bool {
+    p[0].is_finite() && p[1].is_finite()
+}
+
+/// `rejected` are the records the parser skipped; a file with any of them never validates.
+pub fn validate(frames: &[Frame], rejected: &[RecordError], options: ValidationOptions) -> ValidationReport {
+    let target_speed = options.max_target_speed.unwrap_or(options.max_speed);
+    let mut issues = Vec::new();
+
+    for (k, frame) in frames.iter().enumerate() {
+        let t = frame.time;
+        let mut push = |kind| issues.push(Issue { frame: Some(k), time: Some(t), kind });
+
+        if !t.is_finite() {
+            push(IssueKind::NonFiniteTime);
+        }
+        for (agent, &p) in frame.state.agents.iter().enumerate() {
+            if !is_finite(p) {
+                push(IssueKind::NonFinitePosition { body: Body::Agent { agent } });
+            }
+        }
+        if !is_finite(frame.state.target) {
+            push(IssueKind::NonFinitePosition { body: Body::Target });
+        }
+
+        let Some(previous) = k.checked_sub(1).map(|i| &frames[i]) else {
+            continue;
+        };
+        let (from, to) = (previous.agent_count(), frame.agent_count());
+        if from != to {
+            push(IssueKind::AgentCountChanged { from, to });
+        }
+
+        if !(t.is_finite() && previous.time.is_finite()) {
+            continue;
+        }
+        let dt = t - previous.time;
+        if dt < 0.0 {
+            push(IssueKind::TimeReversed { previous: previous.time });
+            continue;
+        }
+        if dt == 0.0 {
+            push(IssueKind::TimeRepeated);
+            continue;
+        }
+
+        // Agents are matched by index; extra or missing ones are covered by the count check
+        let bodies = previous
+            .state
+            .agents
+            .iter()
+            .zip(&frame.state.agents)
+            .enumerate()
+            .map(|(agent, (&a, &b))| (Body::Agent { agent }, a, b, options.max_speed))
+            .chain([(Body::Target, previous.state.target, frame.state.target, target_speed)]);
+        for (body, from, to, bound) in bodies {
+            if !(is_finite(from) && is_finite(to)) {
+                continue;
+            }
+            let d = distance(from, to);
+            let speed = d / dt;
+            if speed > bound {
+                push(IssueKind::Teleport {
+                    body,
+                    from,
+                    to,
+                    distance: d,
+                    dt,
+                    speed,
+                });
+            }
+        }
+    }
+
+    issues.extend(rejected.iter().map(|e| Issue {
+        frame: None,
+        time: None,
+        kind: IssueKind::RejectedRecord { offset: e.offset, line: e.line, message: e.message.clone() },
+    }));
+
+    let mut flagged_frames: Vec<usize> = issues.iter().filter_map(|i| i.frame).collect();
+    flagged_frames.dedup();
+
+    ValidationReport {
+        frames: frames.len(),
+        valid: issues.is_empty(),
+        issues,
+        flagged_frames,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_reader;
+
+    const OPTIONS: ValidationOptions = ValidationOptions { max_speed: 10.0, max_target_speed: None };
+
+    #[test]
+    fn non_finite_tokens_are_reported() {
+        let data = br#"{"time": 0.0, "state": {"agents": [[0, 0]], "target": [0, 0]}},
+{"time": NaN, "state": {"agents": [[Infinity, 0]], "target": [0, -Infinity]}, "signals": [{"rotations": [[NaN, 1]]}]}
+"#;
+        let parsed = parse_reader(&data[..]).unwrap();
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert!(parsed.frames[1].time.is_nan());
+        assert_eq!(parsed.frames[1].state.agents[0][0], f64::INFINITY);
+
+        let report = validate(&parsed.frames, &parsed.errors, OPTIONS);
+        let kinds: Vec<_> = report.issues.iter().map(|i| &i.kind).collect();
+        assert!(matches!(
+            kinds[..],
+            [IssueKind::NonFiniteTime, IssueKind::NonFinitePosition { .. }, IssueKind::NonFinitePosition { .. }]
+        ));
+        assert_eq!(report.flagged_frames, [1]);
+    }
+
+    #[test]
+    fn rejected_records_fail_validation() {
+        let data = br#"{"time": 0.0, "state": {"agents": [[0, 0]], "target": [0, 0]}},
+{"time": "soon", "state": {"agents": [[0, 0]], "target": [0, 0]}},
+{"time": 1.0, "state": {"agents": [[0, 0]], "target": [0, 0]}}
+"#;
+        let parsed = parse_reader(&data[..]).unwrap();
+        let report = validate(&parsed.frames, &parsed.errors, OPTIONS);
+        assert!(!report.valid);
+        assert!(matches!(
+            report.issues[..],
+            [Issue { frame: None, kind: IssueKind::RejectedRecord { line: 2, .. }, .. }]
+        ));
+        assert!(report.flagged_frames.is_empty());
+    }
+}
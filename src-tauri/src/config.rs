@@ -57,4 +57,13 @@ pub fn read_config() -> io::Result<Config> {
         mas_path: String::new(),
     });
     Ok(config)
-}
\ No newline at end of file
+}
+
+// python_path and mas_path from the config, or an error if either is unset
+pub fn mas_command() -> Result<(String, String), String> {
+    let config = read_config().map_err(|e| format!("Failed to read config: {}", e))?;
+    if config.python_path.is_empty() || config.mas_path.is_empty() {
+        return Err("python_path and mas_path must be set in ~/.config/fence/config.json".to_string());
+    }
+    Ok((config.python_path, config.mas_path))
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use manager::{JobId, JobInfo, JobSpec, RunPolicy, Scheduler, SweepDefinition, SweepId, SweepStatus, Sweeps};
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::State;
+
+use crate::config;
+
+// Simulations run at once until the frontend changes it
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+// A simulation run as submitted by the frontend
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobRequest {
+    pub profile: String,
+    // Extra arguments after `simulate <profile>`
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub policy: RunPolicy,
+}
+
+// Turn a request into `python_path mas_path simulate <profile> <args>`
+pub fn simulation_job(request: JobRequest) -> Result<JobSpec, String> {
+    let (python_path, mas_path) = config::mas_command()?;
+    Ok(simulation_spec(&python_path, &mas_path, request))
+}
+
+// Like `simulation_job`, with the interpreter and script already resolved
+fn simulation_spec(python_path: &str, mas_path: &str, request: JobRequest) -> JobSpec {
+    let mut args = vec![mas_path.to_string(), "simulate".to_string(), request.profile.clone()];
+    args.extend(request.args);
+    JobSpec {
+        profile: request.profile,
+        program: python_path.to_string(),
+        args,
+        working_dir: request.working_dir,
+        priority: request.priority,
+        policy: request.policy,
+    }
+}
+
+// Queue a simulation; progress is reported through `job://changed` events
+#[tauri::command]
+pub fn submit_job(request: JobRequest, scheduler: State<Scheduler>) -> Result<JobId, String> {
+    Ok(scheduler.submit(simulation_job(request)?))
+}
+
+// Cancel a queued job, or interrupt a running one
+#[tauri::command]
+pub fn cancel_job(id: JobId, scheduler: State<Scheduler>) -> Result<(), String> {
+    if scheduler.cancel(id) {
+        Ok(())
+    } else {
+        Err(format!("Job {} not found or already finished", id))
+    }
+}
+
+#[tauri::command]
+pub fn get_job(id: JobId, scheduler: State<Scheduler>) -> Result<JobInfo, String> {
+    scheduler.get(id).ok_or_else(|| format!("Job {} not found", id))
+}
+
+#[tauri::command]
+pub fn list_jobs(scheduler: State<Scheduler>) -> Vec<JobInfo> {
+    scheduler.list()
+}
+
+// Ids of queued jobs in the order they will start
+#[tauri::command]
+pub fn queued_jobs(scheduler: State<Scheduler>) -> Vec<JobId> {
+    scheduler.queued()
+}
+
+#[tauri::command]
+pub fn set_job_concurrency(max: usize, scheduler: State<Scheduler>) -> Result<(), String> {
+    if max == 0 {
+        return Err("Concurrency must be at least 1".to_string());
+    }
+    scheduler.set_max_concurrency(max);
+    Ok(())
+}
+
+// Expand a sweep into one profile per point and queue a run for each. Writing the
+// profiles can take a while on large sweeps, so keep it off the main thread.
+#[tauri::command(async)]
+pub fn start_sweep(
+    definition: SweepDefinition,
+    scheduler: State<Scheduler>,
+    sweeps: State<Sweeps>,
+) -> Result<SweepId, String> {
+    let (python_path, mas_path) = config::mas_command()?;
+    sweeps.start(&scheduler, &definition, |point| {
+        Ok(simulation_spec(
+            &python_path,
+            &mas_path,
+            JobRequest {
+                profile: point.profile_path.to_string_lossy().into_owned(),
+                args: definition.args.clone(),
+                working_dir: Some(point.output_dir.clone()),
+                priority: definition.priority,
+                policy: definition.policy,
+            },
+        ))
+    })
+}
+
+// Parameter values of every point, without writing or running anything
+#[tauri::command]
+pub fn preview_sweep(definition: SweepDefinition) -> Result<Vec<BTreeMap<String, Value>>, String> {
+    manager::sweep::expand(&definition)
+}
+
+#[tauri::command]
+pub fn sweep_status(id: SweepId, scheduler: State<Scheduler>, sweeps: State<Sweeps>) -> Result<SweepStatus, String> {
+    sweeps.status(id, &scheduler).ok_or_else(|| format!("Sweep {} not found", id))
+}
+
+#[tauri::command]
+pub fn list_sweeps(scheduler: State<Scheduler>, sweeps: State<Sweeps>) -> Vec<SweepStatus> {
+    sweeps.list(&scheduler)
+}
+
+#[tauri::command]
+pub fn cancel_sweep(id: SweepId, scheduler: State<Scheduler>, sweeps: State<Sweeps>) -> Result<(), String> {
+    if sweeps.cancel(id, &scheduler) {
+        Ok(())
+    } else {
+        Err(format!("Sweep {} not found", id))
+    }
+}
@@ -1,4 +1,17 @@
 mod config;
+mod jobs;
+mod trajectory;
+mod utils;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+use manager::{JobInfo, RunPolicy, Scheduler, Sweeps};
+use tauri::{Emitter, Manager, RunEvent, State};
+use utils::cp::{OutputStream, ProcessEvent, PythonProcessManager, StopPolicy};
+use utils::ring::OutputChunk;
+use utils::usage::{ResourceSample, UsageChunk};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -25,6 +38,58 @@ fn read_file(path: String) -> Result<String, String> {
     std::fs::read_to_string(path).map_err(|e| e.to_string())
 }
 
+// Launch `python_path mas_path <args>` under the given name, limited by `policy` if given
+#[tauri::command]
+fn exec_mas(
+    name: String,
+    args: Vec<String>,
+    policy: Option<RunPolicy>,
+    manager: State<PythonProcessManager>,
+) -> Result<(), String> {
+    let (python_path, mas_path) = config::mas_command()?;
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    manager.add(&name, &python_path, &mas_path, &args, policy.unwrap_or_default())
+}
+
+// Return buffered stdout lines since `cursor`; pass the returned `next` back on the following call
+#[tauri::command]
+fn read_stdout(name: String, cursor: Option<u64>, manager: State<PythonProcessManager>) -> Result<OutputChunk, String> {
+    manager.read(&name, OutputStream::Stdout, cursor.unwrap_or(0))
+}
+
+#[tauri::command]
+fn read_stderr(name: String, cursor: Option<u64>, manager: State<PythonProcessManager>) -> Result<OutputChunk, String> {
+    manager.read(&name, OutputStream::Stderr, cursor.unwrap_or(0))
+}
+
+// Return CPU/memory/I/O samples since `cursor`; pass the returned `next` back on the following call
+#[tauri::command]
+fn mas_usage(name: String, cursor: Option<usize>, manager: State<PythonProcessManager>) -> Result<UsageChunk, String> {
+    manager.usage(&name, cursor.unwrap_or(0))
+}
+
+// Latest sample of every run, keyed by name
+#[tauri::command]
+fn mas_usage_all(manager: State<PythonProcessManager>) -> HashMap<String, ResourceSample> {
+    manager.latest_usage()
+}
+
+#[tauri::command]
+fn mas_exited(name: String, manager: State<PythonProcessManager>) -> bool {
+    manager.is_exited(&name)
+}
+
+// Interrupt the process group, escalating to SIGKILL after the policy's timeout.
+// Waits for the process to exit, so it runs off the main thread.
+#[tauri::command(async)]
+fn stop_mas(name: String, policy: Option<StopPolicy>, manager: State<PythonProcessManager>) -> Result<(), String> {
+    manager.stop(&name, policy)
+}
+
+#[tauri::command]
+fn list_mas(manager: State<PythonProcessManager>) -> Vec<String> {
+    manager.list_processes()
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -32,7 +97,83 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![greet,log_message,get_config,read_file])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .manage(PythonProcessManager::new())
+        .manage(Scheduler::new(jobs::DEFAULT_CONCURRENCY))
+        .manage(Sweeps::default())
+        .manage(trajectory::TrajectoryWatchers::default())
+        .manage(trajectory::OpenTrajectories::default())
+        .setup(|app| {
+            // Forward process events to the frontend as `process://*` events
+            let handle = app.handle().clone();
+            app.state::<PythonProcessManager>().set_event_sink(Arc::new(move |event: &ProcessEvent| {
+                let _ = handle.emit(event.channel(), event);
+            }));
+            // Forward job state changes as `job://changed`
+            let handle = app.handle().clone();
+            app.state::<Scheduler>().set_listener(Arc::new(move |job: &JobInfo| {
+                let _ = handle.emit("job://changed", job);
+            }));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            log_message,
+            get_config,
+            read_file,
+            exec_mas,
+            read_stdout,
+            read_stderr,
+            mas_usage,
+            mas_usage_all,
+            mas_exited,
+            stop_mas,
+            list_mas,
+            jobs::submit_job,
+            jobs::cancel_job,
+            jobs::get_job,
+            jobs::list_jobs,
+            jobs::queued_jobs,
+            jobs::set_job_concurrency,
+            jobs::start_sweep,
+            jobs::preview_sweep,
+            jobs::sweep_status,
+            jobs::list_sweeps,
+            jobs::cancel_sweep,
+            trajectory::load_trajectory,
+            trajectory::encirclement_metrics,
+            trajectory::angular_spacing,
+            trajectory::target_containment,
+            trajectory::separation_events,
+            trajectory::kinematics,
+            trajectory::formation_rotation,
+            trajectory::validate_trajectory,
+            trajectory::resample_trajectory,
+            trajectory::compare_trajectories,
+            trajectory::spectral_analysis,
+            trajectory::communication_graph,
+            trajectory::export_trajectory,
+            trajectory::batch_statistics,
+            trajectory::run_summary,
+            trajectory::open_trajectory,
+            trajectory::get_frame,
+            trajectory::get_frames,
+            trajectory::frame_at_time,
+            trajectory::watch_trajectory,
+            trajectory::unwatch_trajectory
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Give running simulations the chance to flush their output before the app goes away.
+            // Both wait out their grace period and kill what is left before returning, side by side.
+            if let RunEvent::Exit = event {
+                let (scheduler, processes) = (app.state::<Scheduler>(), app.state::<PythonProcessManager>());
+                thread::scope(|scope| {
+                    scope.spawn(|| scheduler.shutdown());
+                    if let Err(e) = processes.stop_all() {
+                        eprintln!("{}", e);
+                    }
+                });
+            }
+        });
 }
@@ -0,0 +1,342 @@
+// Commands for reading and analysing `realtime.json1` trajectories
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+// How often a watched file is checked for new frames
+const TAIL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Read every frame of `path`. The first load writes a binary cache next to the file;
+// later loads map it instead of parsing JSON.
+fn load_parsed(path: &str) -> Result<mas::Parsed, String> {
+    if let Ok((binary, errors)) = mas::BinaryTrajectory::open_or_convert(path) {
+        if let Ok(frames) = binary.frames() {
+            return Ok(mas::Parsed { frames, errors });
+        }
+    }
+
+    // Runs whose agent count changes cannot be cached; read the JSON directly
+    mas::load_trajectory(path).map_err(|e| format!("Failed to load {}: {}", path, e))
+}
+
+fn load_frames(path: &str) -> Result<Vec<mas::Frame>, String> {
+    load_parsed(path).map(|parsed| parsed.frames)
+}
+
+// Parsing can take a while on long runs, so keep it off the main thread
+#[tauri::command(async)]
+pub fn load_trajectory(path: String) -> Result<mas::Parsed, String> {
+    load_parsed(&path)
+}
+
+#[tauri::command(async)]
+pub fn encirclement_metrics(path: String, options: mas::RadiusOptions) -> Result<mas::RadiusMetrics, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::radius_metrics(&frames, options))
+}
+
+// `tolerance` bounds `1 - uniformity` for the formation to count as settled
+#[tauri::command(async)]
+pub fn angular_spacing(path: String, tolerance: f64) -> Result<mas::SpacingAnalysis, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::angular_spacing(&frames, tolerance))
+}
+
+#[tauri::command(async)]
+pub fn target_containment(path: String) -> Result<mas::Containment, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::containment(&frames))
+}
+
+// Minimum separation per frame and the list of too-close events, each with frame indices to jump to
+#[tauri::command(async)]
+pub fn separation_events(path: String, options: mas::SeparationOptions) -> Result<mas::SeparationAnalysis, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::separation(&frames, options))
+}
+
+// Velocity, acceleration, jerk and control effort derived from positions
+#[tauri::command(async)]
+pub fn kinematics(path: String, scheme: mas::DifferenceScheme) -> Result<mas::Kinematics, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::kinematics(&frames, scheme))
+}
+
+// Angular velocity of the formation about the target, windowed statistics and direction reversals
+#[tauri::command(async)]
+pub fn formation_rotation(path: String, options: mas::RotationOptions) -> Result<mas::RotationAnalysis, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::formation_rotation(&frames, options))
+}
+
+// Flags teleports, non-monotonic time, agent count changes and NaN/Inf before a run is analysed
+#[tauri::command(async)]
+pub fn validate_trajectory(path: String, options: mas::ValidationOptions) -> Result<mas::ValidationReport, String> {
+    let parsed = load_parsed(&path)?;
+    Ok(mas::validate(&parsed.frames, &parsed.errors, options))
+}
+
+// Interpolated frames on a uniform or explicit time grid, so runs with different steps line up
+#[tauri::command(async)]
+pub fn resample_trajectory(path: String, options: mas::ResampleOptions) -> Result<Vec<mas::Frame>, String> {
+    let frames = load_frames(&path)?;
+    mas::resample(&frames, &options)
+}
+
+// Aligns two runs on a common grid and reports how far and from when they differ
+#[tauri::command(async)]
+pub fn compare_trajectories(a: String, b: String, options: mas::CompareOptions) -> Result<mas::Comparison, String> {
+    let frames_a = load_frames(&a)?;
+    let frames_b = load_frames(&b)?;
+    mas::compare(&frames_a, &frames_b, options)
+}
+
+// Welch spectra of each agent's radius error and bearing, with dominant frequencies and damping
+#[tauri::command(async)]
+pub fn spectral_analysis(path: String, options: mas::SpectrumOptions) -> Result<mas::SpectralAnalysis, String> {
+    let frames = load_frames(&path)?;
+    mas::spectral_analysis(&frames, options)
+}
+
+// r-disk communication graph per frame: connectivity, Fiedler value, degrees and disconnections
+#[tauri::command(async)]
+pub fn communication_graph(path: String, options: mas::GraphOptions) -> Result<mas::GraphAnalysis, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::communication_graph(&frames, options))
+}
+
+// Per-run metrics for every file, loaded on all cores. Files that fail to load are
+// reported as failed runs rather than failing the batch.
+fn batch_runs(paths: &[String], options: &mas::BatchOptions) -> Vec<mas::RunMetrics> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<mas::RunMetrics>>> = Mutex::new(paths.iter().map(|_| None).collect());
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(paths.len());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+                let run = match load_frames(path) {
+                    Ok(frames) => mas::RunMetrics::compute(path, &frames, options),
+                    Err(e) => mas::RunMetrics::failed(path, e),
+                };
+                results.lock().unwrap()[i] = Some(run);
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+// `.tsv` files are tab separated, anything else comma separated
+fn table_delimiter(path: &Path) -> char {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("tsv") => '\t',
+        _ => ',',
+    }
+}
+
+// Writes the per-run table to `path` and the distributions next to it as
+// `<stem>.summary.<ext>`.
+fn export_batch(stats: &mas::BatchStatistics, path: &str) -> Result<(), String> {
+    let path = Path::new(path);
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("csv");
+    let delimiter = table_delimiter(path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("batch");
+    let summary_path = path.with_file_name(format!("{}.summary.{}", stem, ext));
+
+    let write = |target: &Path, table: &dyn Fn(&mut BufWriter<File>) -> std::io::Result<()>| {
+        File::create(target)
+            .map(BufWriter::new)
+            .and_then(|mut out| {
+                table(&mut out)?;
+                out.flush()
+            })
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))
+    };
+    write(path, &|out| stats.write_runs_table(out, delimiter))?;
+    write(&summary_path, &|out| stats.write_summary_table(out, delimiter))
+}
+
+// Aggregate statistics over a Monte Carlo batch, optionally exported as tables
+#[tauri::command(async)]
+pub fn batch_statistics(
+    paths: Vec<String>,
+    options: mas::BatchOptions,
+    export: Option<String>,
+) -> Result<mas::BatchStatistics, String> {
+    let stats = mas::aggregate(batch_runs(&paths, &options), &options.percentiles);
+    if let Some(path) = export {
+        export_batch(&stats, &path)?;
+    }
+    Ok(stats)
+}
+
+// Streams the run into a CSV/TSV table without loading it whole, for Excel and pandas
+#[tauri::command(async)]
+pub fn export_trajectory(path: String, output: String, options: mas::ExportOptions) -> Result<mas::ExportSummary, String> {
+    let input = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let output_path = Path::new(&output);
+    let mut out = File::create(output_path)
+        .map(BufWriter::new)
+        .map_err(|e| format!("Failed to create {}: {}", output, e))?;
+
+    mas::export_table(mas::FrameReader::new(input), &mut out, &options, table_delimiter(output_path))
+        .and_then(|summary| out.flush().map(|_| summary))
+        .map_err(|e| format!("Failed to export {}: {}", path, e))
+}
+
+#[tauri::command(async)]
+pub fn run_summary(path: String) -> Result<mas::RunSummary, String> {
+    let frames = load_frames(&path)?;
+    Ok(mas::summarize(&frames))
+}
+
+// Trajectories opened for random access, keyed by path
+#[derive(Default)]
+pub struct OpenTrajectories {
+    files: Mutex<HashMap<String, Arc<Mutex<mas::IndexedTrajectory>>>>,
+}
+
+impl OpenTrajectories {
+    // Open `path` on first use; afterwards pick up any frames appended since
+    fn get(&self, path: &str) -> Result<Arc<Mutex<mas::IndexedTrajectory>>, String> {
+        let existing = self.files.lock().unwrap().get(path).cloned();
+        if let Some(trajectory) = existing {
+            trajectory.lock().unwrap().refresh().map_err(|e| format!("Failed to refresh {}: {}", path, e))?;
+            return Ok(trajectory);
+        }
+
+        let trajectory = mas::IndexedTrajectory::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let trajectory = Arc::new(Mutex::new(trajectory));
+        self.files.lock().unwrap().insert(path.to_string(), Arc::clone(&trajectory));
+        Ok(trajectory)
+    }
+}
+
+#[derive(Serialize)]
+pub struct TrajectorySummary {
+    frames: usize,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+}
+
+// Build (or load the cached) frame index for `path`
+#[tauri::command(async)]
+pub fn open_trajectory(path: String, open: State<'_, OpenTrajectories>) -> Result<TrajectorySummary, String> {
+    let trajectory = open.get(&path)?;
+    let trajectory = trajectory.lock().unwrap();
+    let entries = &trajectory.index().entries;
+    Ok(TrajectorySummary {
+        frames: entries.len(),
+        start_time: entries.first().map(|e| e.time),
+        end_time: entries.last().map(|e| e.time),
+    })
+}
+
+#[tauri::command(async)]
+pub fn get_frame(path: String, index: usize, open: State<'_, OpenTrajectories>) -> Result<mas::Frame, String> {
+    let trajectory = open.get(&path)?;
+    let frame = trajectory.lock().unwrap().frame(index);
+    frame.map_err(|e| e.to_string())
+}
+
+// Frames `start..end`; the range is clamped to the frames that exist
+#[tauri::command(async)]
+pub fn get_frames(path: String, start: usize, end: usize, open: State<'_, OpenTrajectories>) -> Result<Vec<mas::Frame>, String> {
+    let trajectory = open.get(&path)?;
+    let frames = trajectory.lock().unwrap().frames(start..end);
+    frames.map_err(|e| e.to_string())
+}
+
+// Index of the last frame at or before `time`
+#[tauri::command(async)]
+pub fn frame_at_time(path: String, time: f64, open: State<'_, OpenTrajectories>) -> Result<Option<usize>, String> {
+    let trajectory = open.get(&path)?;
+    let index = trajectory.lock().unwrap().frame_at_time(time);
+    Ok(index)
+}
+
+// Files currently being tailed, keyed by path
+#[derive(Default)]
+pub struct TrajectoryWatchers {
+    watchers: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+// Payload of the `trajectory://frames` event
+#[derive(Clone, Serialize)]
+struct FramesEvent {
+    path: String,
+    #[serde(flatten)]
+    batch: mas::TailBatch,
+}
+
+// Start following `path`, emitting `trajectory://frames` for every batch of new frames
+#[tauri::command]
+pub fn watch_trajectory(path: String, app: AppHandle, watchers: State<TrajectoryWatchers>) -> Result<(), String> {
+    let mut watchers = watchers.watchers.lock().unwrap();
+    if watchers.contains_key(&path) {
+        return Err(format!("{} is already being watched", path));
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    watchers.insert(path.clone(), Arc::clone(&running));
+
+    thread::spawn(move || {
+        let mut follower = mas::TailFollower::new(&path);
+        // Only report an error when it differs from the previous poll's, not every interval
+        let mut last_error: Option<String> = None;
+        while running.load(Ordering::Relaxed) {
+            match follower.poll() {
+                Ok(batch) => {
+                    last_error = None;
+                    if !batch.frames.is_empty() || !batch.errors.is_empty() || batch.restarted {
+                        let _ = app.emit("trajectory://frames", FramesEvent { path: path.clone(), batch });
+                    }
+                }
+                Err(e) => {
+                    let message = format!("{}: {}", path, e);
+                    if last_error.as_ref() != Some(&message) {
+                        let _ = app.emit("trajectory://error", message.clone());
+                        last_error = Some(message);
+                    }
+                }
+            }
+            thread::sleep(TAIL_INTERVAL);
+        }
+
+        // The watch is over, so a record still left half written will not be completed
+        let errors = follower.finish();
+        if !errors.is_empty() {
+            let batch = mas::TailBatch {
+                first_index: follower.frames_seen(),
+                errors,
+                ..Default::default()
+            };
+            let _ = app.emit("trajectory://frames", FramesEvent { path, batch });
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unwatch_trajectory(path: String, watchers: State<TrajectoryWatchers>) -> Result<(), String> {
+    match watchers.watchers.lock().unwrap().remove(&path) {
+        Some(running) => {
+            running.store(false, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err(format!("{} is not being watched", path)),
+    }
+}
@@ -1,18 +1,376 @@
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Stdout};
-use std::process::{Child, Command, Stdio,ChildStderr, ChildStdout};
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use manager::policy::{
+    exit_limit, exit_signal, is_memory_error, prepare_command, signal_group, GroupSignal, ResourceLimit, RunPolicy,
+    Violation, Watchdog,
+};
+use serde::{Deserialize, Serialize};
+
+use super::ring::{OutputChunk, OutputRing, DEFAULT_RING_BYTES};
+use super::usage::{ResourceSample, TreeSampler, UsageChunk, UsageHistory, SAMPLE_INTERVAL};
+
+// 输出事件的合并间隔，高频输出在此间隔内合并为一个事件
+const COALESCE_INTERVAL: Duration = Duration::from_millis(50);
+// 进程退出后等待管道关闭的最长时间（管道可能被孙进程继承）
+const EXIT_GRACE: Duration = Duration::from_secs(1);
+// 停止进程时轮询退出状态的间隔
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// 事件回调，由 Tauri 层注册，负责把事件发送到前端
+pub type EventSink = Arc<dyn Fn(&ProcessEvent) + Send + Sync>;
 
-#[derive(Debug)]
 pub struct PythonProcessManager {
     processes: Mutex<HashMap<String, Arc<Mutex<ProcessInfo>>>>,
+    sink: Arc<Mutex<Option<EventSink>>>,
 }
 
 #[derive(Debug)]
 struct ProcessInfo {
     child: Child,
-    stdout_reader: Option<BufReader<ChildStdout>>,
+    started: Instant,
+    stdout: Arc<Mutex<OutputRing>>,
+    stderr: Arc<Mutex<OutputRing>>,
+    readers: Vec<JoinHandle<()>>,
+    // 按运行策略检查墙钟时间与输出上限
+    watchdog: Watchdog,
+    // stdout 与 stderr 累计读取的字节数
+    output_bytes: Arc<AtomicU64>,
+    // 由 stop 或看门狗记录的退出原因；为空表示进程仍在运行或自行退出
+    reason: Option<ExitReason>,
+    // finish_stop 正在结束进程组，由它回收主进程，监视线程暂不 try_wait
+    stopping: bool,
+    limit: Option<ResourceLimit>,
+    // 运行期间的资源占用采样
+    usage: UsageHistory,
+    // 监视线程已推送 exited 事件；此后条目只用于读取输出和资源占用，名称可以被新进程复用
+    reported: bool,
+}
+
+// 停止进程时先发送的信号
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopSignal {
+    Interrupt,
+    Terminate,
+}
+
+// 停止策略：先发送 signal，等待 timeout_ms 后仍未退出则对整个进程组发送 SIGKILL
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StopPolicy {
+    pub signal: StopSignal,
+    pub timeout_ms: u64,
+}
+
+impl Default for StopPolicy {
+    // SIGINT 让 Python 抛出 KeyboardInterrupt，mas 有机会写完 realtime.json1
+    fn default() -> Self {
+        Self {
+            signal: StopSignal::Interrupt,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+// 进程退出的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    // 自行退出
+    Exited,
+    // 收到停止信号后在超时前退出
+    Stopped,
+    // 超时后被 SIGKILL 强制结束
+    Killed,
+    // 超过运行策略的墙钟时间
+    TimedOut,
+    // 超过运行策略的资源限制，具体见 limit
+    LimitExceeded,
+}
+
+// 子进程的输出流
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+// 推送给前端的进程事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ProcessEvent {
+    Started {
+        name: String,
+        pid: u32,
+    },
+    Output {
+        name: String,
+        stream: OutputStream,
+        #[serde(flatten)]
+        chunk: OutputChunk,
+    },
+    Usage {
+        name: String,
+        #[serde(flatten)]
+        sample: ResourceSample,
+    },
+    Exited {
+        name: String,
+        code: Option<i32>,
+        signal: Option<i32>,
+        reason: ExitReason,
+        limit: Option<ResourceLimit>,
+        duration_ms: u64,
+    },
+}
+
+impl ProcessEvent {
+    // 事件对应的 Tauri 事件名
+    pub fn channel(&self) -> &'static str {
+        match self {
+            ProcessEvent::Started { .. } => "process://started",
+            ProcessEvent::Output { stream: OutputStream::Stdout, .. } => "process://stdout",
+            ProcessEvent::Output { stream: OutputStream::Stderr, .. } => "process://stderr",
+            ProcessEvent::Usage { .. } => "process://usage",
+            ProcessEvent::Exited { .. } => "process://exited",
+        }
+    }
+}
+
+fn emit(sink: &Mutex<Option<EventSink>>, event: ProcessEvent) {
+    let sink = sink.lock().unwrap().clone();
+    if let Some(sink) = sink {
+        sink(&event);
+    }
+}
+
+// 取出 cursor 之后的新输出，有内容时发送事件
+fn flush_output(
+    sink: &Mutex<Option<EventSink>>,
+    name: &str,
+    stream: OutputStream,
+    ring: &Mutex<OutputRing>,
+    cursor: &mut u64,
+) {
+    let chunk = ring.lock().unwrap().read_since(*cursor);
+    if chunk.next == *cursor {
+        return;
+    }
+    *cursor = chunk.next;
+    emit(sink, ProcessEvent::Output { name: name.to_string(), stream, chunk });
+}
+
+// 向进程组发送停止信号；非 Unix 平台没有信号，整个进程树直接被结束。
+// 已经自行退出的进程保留原来的退出原因，不再发送信号：主进程已被回收，
+// 它的 pid 可能已被复用。返回是否向仍在运行的进程发送了信号
+fn request_stop(info: &mut ProcessInfo, signal: StopSignal) -> bool {
+    if let Ok(Some(_)) = info.child.try_wait() {
+        return false;
+    }
+    let signal = match signal {
+        StopSignal::Interrupt => GroupSignal::Interrupt,
+        StopSignal::Terminate => GroupSignal::Terminate,
+    };
+    info.reason.get_or_insert(if cfg!(unix) { ExitReason::Stopped } else { ExitReason::Killed });
+    info.stopping = true;
+    signal_group(info.child.id(), signal);
+    true
+}
+
+// 不回收主进程地检查它是否已退出：回收之前进程组 id 不会被复用，仍可以安全地发送信号
+#[cfg(unix)]
+fn leader_exited(info: &mut ProcessInfo) -> io::Result<bool> {
+    manager::policy::has_exited(info.child.id())
+}
+
+#[cfg(not(unix))]
+fn leader_exited(info: &mut ProcessInfo) -> io::Result<bool> {
+    info.child.try_wait().map(|status| status.is_some())
+}
+
+// 强制结束整个进程组
+fn kill_group(info: &mut ProcessInfo) {
+    signal_group(info.child.id(), GroupSignal::Kill);
+}
+
+// 等待已收到停止信号的进程在 deadline 前退出，超时则强制结束；
+// 主进程退出后仍残留的子孙进程也一并结束。只用于 request_stop 刚刚打断的进程：
+// 主进程由这里在向进程组发送信号之后才回收，此时进程组仍属于它
+fn finish_stop(info: &Mutex<ProcessInfo>, deadline: Instant) -> Result<(), String> {
+    let result = loop {
+        let mut info = info.lock().unwrap();
+        match leader_exited(&mut info) {
+            Ok(true) => break reap_group(&mut info),
+            Ok(false) if Instant::now() < deadline => {}
+            Ok(false) => {
+                if info.reason == Some(ExitReason::Stopped) {
+                    info.reason = Some(ExitReason::Killed);
+                }
+                break reap_group(&mut info);
+            }
+            Err(e) => break Err(e),
+        }
+        drop(info);
+        thread::sleep(STOP_POLL_INTERVAL);
+    };
+
+    let mut info = info.lock().unwrap();
+    info.stopping = false;
+    info.join_readers();
+    result.map_err(|e| format!("Failed to wait for process exit: {}", e))
+}
+
+// 结束进程组中剩余的进程，然后回收主进程
+fn reap_group(info: &mut ProcessInfo) -> io::Result<()> {
+    kill_group(info);
+    info.child.wait().map(|_| ())
+}
+
+// 等待监视线程推送已退出进程的 exited 事件，最多等待一个退出宽限期
+fn wait_reported(info: &Mutex<ProcessInfo>) {
+    let deadline = Instant::now() + EXIT_GRACE + COALESCE_INTERVAL * 2;
+    while !info.lock().unwrap().reported && Instant::now() < deadline {
+        thread::sleep(STOP_POLL_INTERVAL);
+    }
+}
+
+// 看门狗：超过墙钟时间时中断进程组，宽限期后仍未退出则强制结束；超过输出上限时直接强制结束。
+// 由 stop 发起的停止交给 finish_stop 处理
+fn enforce_policy(info: &mut ProcessInfo) {
+    if info.reason.is_some() && info.watchdog.violation().is_none() {
+        return;
+    }
+    match info.watchdog.check(info.output_bytes.load(Ordering::Relaxed)) {
+        Some(Violation::TimedOut) => info.reason = Some(ExitReason::TimedOut),
+        Some(Violation::LimitExceeded(limit)) => {
+            info.reason = Some(ExitReason::LimitExceeded);
+            info.limit = Some(limit);
+        }
+        None => {}
+    }
+}
+
+// 判断自行退出的进程是否因 rlimit 而结束：CPU 超限由 SIGXCPU 结束，
+// 地址空间超限时 Python 通常以 MemoryError 退出
+fn classify_exit(info: &ProcessInfo, status: &std::process::ExitStatus) -> (ExitReason, Option<ResourceLimit>) {
+    if let Some(reason) = info.reason {
+        return (reason, info.limit);
+    }
+    let memory_error = || is_memory_error(&info.stderr.lock().unwrap().read_since(0).text);
+    match exit_limit(info.watchdog.policy(), status, memory_error) {
+        Some(limit) => (ExitReason::LimitExceeded, Some(limit)),
+        None => (ExitReason::Exited, None),
+    }
+}
+
+// 每个进程一个监视线程：按合并间隔推送新输出，运行期间按采样间隔推送资源占用，
+// 进程退出后推送 exited 事件
+fn spawn_monitor(name: String, info: Arc<Mutex<ProcessInfo>>, sink: Arc<Mutex<Option<EventSink>>>) {
+    thread::spawn(move || {
+        let (stdout, stderr, started, pid) = {
+            let info = info.lock().unwrap();
+            (Arc::clone(&info.stdout), Arc::clone(&info.stderr), info.started, info.child.id())
+        };
+        let mut stdout_cursor = 0;
+        let mut stderr_cursor = 0;
+        let mut sampler = TreeSampler::new(pid, started);
+        let mut next_sample = started + SAMPLE_INTERVAL;
+        let mut exited_at: Option<(Instant, std::process::ExitStatus)> = None;
+
+        loop {
+            thread::sleep(COALESCE_INTERVAL);
+
+            flush_output(&sink, &name, OutputStream::Stdout, &stdout, &mut stdout_cursor);
+            flush_output(&sink, &name, OutputStream::Stderr, &stderr, &mut stderr_cursor);
+
+            if exited_at.is_none() {
+                let mut process = info.lock().unwrap();
+                if process.stopping {
+                    continue;
+                }
+                match process.child.try_wait() {
+                    Ok(Some(status)) => exited_at = Some((Instant::now(), status)),
+                    Ok(None) => {
+                        enforce_policy(&mut process);
+                        drop(process);
+                        // 读取 /proc 时不持有进程锁
+                        if Instant::now() >= next_sample {
+                            next_sample = Instant::now() + SAMPLE_INTERVAL;
+                            if let Some(sample) = sampler.sample() {
+                                info.lock().unwrap().usage.push(sample.clone());
+                                emit(&sink, ProcessEvent::Usage { name: name.clone(), sample });
+                            }
+                        }
+                        continue;
+                    }
+                    Err(_) => {
+                        process.reported = true;
+                        break;
+                    }
+                }
+            }
+
+            if let Some((at, status)) = exited_at {
+                let drained = stdout.lock().unwrap().is_closed() && stderr.lock().unwrap().is_closed();
+                if !drained && at.elapsed() < EXIT_GRACE {
+                    continue;
+                }
+
+                flush_output(&sink, &name, OutputStream::Stdout, &stdout, &mut stdout_cursor);
+                flush_output(&sink, &name, OutputStream::Stderr, &stderr, &mut stderr_cursor);
+                let (reason, limit) = {
+                    let mut info = info.lock().unwrap();
+                    let (reason, limit) = classify_exit(&info, &status);
+                    info.reason = Some(reason);
+                    info.limit = limit;
+                    (reason, limit)
+                };
+                emit(
+                    &sink,
+                    ProcessEvent::Exited {
+                        name,
+                        code: status.code(),
+                        signal: exit_signal(&status),
+                        reason,
+                        limit,
+                        duration_ms: at.duration_since(started).as_millis() as u64,
+                    },
+                );
+                info.lock().unwrap().reported = true;
+                break;
+            }
+        }
+    });
+}
+
+// 在后台线程中持续读取管道，写入环形缓冲区，避免管道写满导致子进程阻塞
+fn spawn_reader<R: Read + Send + 'static>(
+    pipe: R,
+    ring: Arc<Mutex<OutputRing>>,
+    output_bytes: Arc<AtomicU64>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    output_bytes.fetch_add(n as u64, Ordering::Relaxed);
+                    let line = String::from_utf8_lossy(&buf).into_owned();
+                    ring.lock().unwrap().push(line);
+                }
+            }
+        }
+        ring.lock().unwrap().close();
+    })
 }
 
 impl PythonProcessManager {
@@ -20,24 +378,40 @@ impl PythonProcessManager {
     pub fn new() -> Self {
         Self {
             processes: Mutex::new(HashMap::new()),
+            sink: Arc::new(Mutex::new(None)),
         }
     }
 
-    // 添加并运行 Python 程序
-    pub fn add(&self, name: &str, python_path: &str, script_path: &str, args: &[&str]) -> Result<(), String> {
+    // 注册事件回调
+    pub fn set_event_sink(&self, sink: EventSink) {
+        *self.sink.lock().unwrap() = Some(sink);
+    }
+
+    // 添加并按运行策略运行 Python 程序
+    pub fn add(
+        &self,
+        name: &str,
+        python_path: &str,
+        script_path: &str,
+        args: &[&str],
+        policy: RunPolicy,
+    ) -> Result<(), String> {
         let mut processes = self.processes.lock().unwrap();
         
-        // 检查名称是否已存在
-        if processes.contains_key(name) {
+        // 检查名称是否已被仍在运行的进程占用；已退出的同名条目会被替换
+        if processes.get(name).is_some_and(|info| !info.lock().unwrap().reported) {
             return Err(format!("Process with name '{}' already exists", name));
         }
         
-        // 构建命令，捕获 stdout
+        // 构建命令，捕获 stdout 和 stderr
         let mut cmd = Command::new(python_path);
         cmd.arg(script_path);
         cmd.args(args);
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
+
+        // 放入独立的进程组，停止时可以连同 Python 派生的子进程一起结束；同时设置 rlimit
+        prepare_command(&mut cmd, &policy);
         
         // 启动进程
         let mut child = match cmd.spawn() {
@@ -45,105 +419,93 @@ impl PythonProcessManager {
             Err(e) => return Err(format!("Failed to start process: {}", e)),
         };
         
-        // 获取 stdout 句柄
+        // 获取 stdout / stderr 句柄
         let stdout = match child.stdout.take() {
             Some(stdout) => stdout,
             None => return Err("Failed to capture stdout".to_string()),
         };
-        
-        // 创建带缓冲的读取器
-        let stdout_reader = Some(BufReader::new(stdout));
-        
+        let stderr = match child.stderr.take() {
+            Some(stderr) => stderr,
+            None => return Err("Failed to capture stderr".to_string()),
+        };
+
+        // 启动后台读取线程
+        let stdout_ring = Arc::new(Mutex::new(OutputRing::new(DEFAULT_RING_BYTES)));
+        let stderr_ring = Arc::new(Mutex::new(OutputRing::new(DEFAULT_RING_BYTES)));
+        let output_bytes = Arc::new(AtomicU64::new(0));
+        let readers = vec![
+            spawn_reader(stdout, Arc::clone(&stdout_ring), Arc::clone(&output_bytes)),
+            spawn_reader(stderr, Arc::clone(&stderr_ring), Arc::clone(&output_bytes)),
+        ];
+
         // 存储进程信息
-        processes.insert(
-            name.to_string(),
-            Arc::new(Mutex::new(ProcessInfo {
-                child,
-                stdout_reader,
-            })),
-        );
+        let pid = child.id();
+        let info = Arc::new(Mutex::new(ProcessInfo {
+            child,
+            started: Instant::now(),
+            stdout: stdout_ring,
+            stderr: stderr_ring,
+            readers,
+            watchdog: Watchdog::new(pid, policy),
+            output_bytes,
+            reason: None,
+            stopping: false,
+            limit: None,
+            usage: UsageHistory::default(),
+            reported: false,
+        }));
+        processes.insert(name.to_string(), Arc::clone(&info));
+
+        emit(&self.sink, ProcessEvent::Started { name: name.to_string(), pid });
+        spawn_monitor(name.to_string(), info, Arc::clone(&self.sink));
         
         Ok(())
     }
 
-    // 读取指定进程的标准输出
-    pub fn read(&self, name: &str) -> Result<Option<String>, String> {
-        let processes = self.processes.lock().unwrap();
-        
-        // 检查进程是否存在
-        let process_info = match processes.get(name) {
-            Some(info) => info,
-            None => return Err(format!("Process with name '{}' not found", name)),
-        };
-        
-        let mut process_info = process_info.lock().unwrap();
-        
-        // 检查 stdout reader 是否存在
-        let reader = match &mut process_info.stdout_reader {
-            Some(reader) => reader,
-            None => return Ok(None),
+    // 读取指定进程某个输出流中序号 >= cursor 的内容（不阻塞）
+    pub fn read(&self, name: &str, stream: OutputStream, cursor: u64) -> Result<OutputChunk, String> {
+        let ring = {
+            let processes = self.processes.lock().unwrap();
+
+            // 检查进程是否存在
+            let process_info = match processes.get(name) {
+                Some(info) => info,
+                None => return Err(format!("Process with name '{}' not found", name)),
+            };
+
+            let process_info = process_info.lock().unwrap();
+            match stream {
+                OutputStream::Stdout => Arc::clone(&process_info.stdout),
+                OutputStream::Stderr => Arc::clone(&process_info.stderr),
+            }
         };
-        
-        // 尝试读取一行输出
-        let mut line = String::new();
-        match reader.read_line(&mut line) {
-            Ok(0) => {
-                // 读取到 EOF，可能进程已退出
-                Ok(None)
-            },
-            Ok(_) => Ok(Some(line)),
-            Err(e) => Err(format!("Failed to read stdout: {}", e)),
-        }
+
+        let chunk = ring.lock().unwrap().read_since(cursor);
+        Ok(chunk)
     }
 
-    // 读取指定进程的所有可用输出（非阻塞）
-    pub fn read_all(&self, name: &str) -> Result<Option<String>, String> {
+    // 读取指定进程序号 >= cursor 的资源占用采样，进程退出后仍可读取完整记录
+    pub fn usage(&self, name: &str, cursor: usize) -> Result<UsageChunk, String> {
         let processes = self.processes.lock().unwrap();
-        
-        // 检查进程是否存在
-        let process_info = match processes.get(name) {
-            Some(info) => info,
-            None => return Err(format!("Process with name '{}' not found", name)),
-        };
-        
-        let mut process_info = process_info.lock().unwrap();
-        
-        // 检查 stdout reader 是否存在
-        let reader = match &mut process_info.stdout_reader {
-            Some(reader) => reader,
-            None => return Ok(None),
-        };
-        
-        let mut all_output = String::new();
-        let mut buffer = String::new();
-        
-        // 非阻塞读取所有可用行
-        loop {
-            // 保存当前位置
-            let pos = match reader.buffer().len() {
-                0 => break,  // 没有更多数据
-                n => n,
-            };
-            
-            // 尝试读取一行
-            match reader.read_line(&mut buffer) {
-                Ok(0) => break,  // 读取到 EOF
-                Ok(_) => {
-                    all_output.push_str(&buffer);
-                    buffer.clear();
-                },
-                Err(e) => return Err(format!("Failed to read stdout: {}", e)),
-            }
-        }
-        
-        if all_output.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(all_output))
+        match processes.get(name) {
+            Some(info) => Ok(info.lock().unwrap().usage.read_since(cursor)),
+            None => Err(format!("Process with name '{}' not found", name)),
         }
     }
 
-    pub fn is_exited(&mut self,name:&str) -> bool {
+    // 所有进程最近一次的资源占用采样，便于找出占用最多的运行
+    pub fn latest_usage(&self) -> HashMap<String, ResourceSample> {
+        let processes = self.processes.lock().unwrap();
+        processes
+            .iter()
+            .filter_map(|(name, info)| {
+                let sample = info.lock().unwrap().usage.latest().cloned()?;
+                Some((name.clone(), sample))
+            })
+            .collect()
+    }
+
+    pub fn is_exited(&self, name: &str) -> bool {
         let processes = self.processes.lock().unwrap();
         if let Some(process_info) = processes.get(name) {
             let mut process_info = process_info.lock().unwrap();
@@ -157,56 +519,50 @@ impl PythonProcessManager {
         }
     }
 
-    // 停止指定名称的进程
-    pub fn stop(&self, name: &str) -> Result<(), String> {
-        let mut processes = self.processes.lock().unwrap();
-        
-        // 检查进程是否存在
-        // 先移除进程，避免双重可变借用
-        let process_info = match processes.remove(name) {
-            Some(info) => info,
+    // 按停止策略停止指定名称的进程，policy 为空时使用默认策略。
+    // 条目保留在列表中，停止后仍可读取最后的输出和资源占用；返回时名称已可复用
+    pub fn stop(&self, name: &str, policy: Option<StopPolicy>) -> Result<(), String> {
+        // 等待退出期间不占用列表锁
+        let process_info = match self.processes.lock().unwrap().get(name) {
+            Some(info) => Arc::clone(info),
             None => return Err(format!("Process with name '{}' not found", name)),
         };
+        let policy = policy.unwrap_or_default();
 
-        let mut process_info = process_info.lock().unwrap();
-
-        // 尝试终止进程
-        match process_info.child.kill() {
-            Ok(_) => {
-                // 等待进程退出
-                match process_info.child.wait() {
-                    Ok(_status) => Ok(()),
-                    Err(e) => Err(format!("Failed to wait for process exit: {}", e)),
-                }
-            },
-            Err(e) => Err(format!("Failed to kill process: {}", e)),
+        if request_stop(&mut process_info.lock().unwrap(), policy.signal) {
+            finish_stop(&process_info, Instant::now() + Duration::from_millis(policy.timeout_ms))?;
+        } else {
+            // 已经退出，只需收尾
+            process_info.lock().unwrap().join_readers();
         }
+        wait_reported(&process_info);
+        Ok(())
     }
 
-    // 停止所有进程
+    // 停止所有进程：先向所有仍在运行的进程发送停止信号，再共用同一个超时等待它们退出；
+    // 已经退出的进程直接跳过
     pub fn stop_all(&self) -> Result<(), String> {
-        let mut processes = self.processes.lock().unwrap();
-        let mut errors = Vec::new();
-        
-        // 尝试终止所有进程
-        for (name, process_info) in processes.iter_mut() {
-            let mut process_info = process_info.lock().unwrap();
-            
-            if let Err(e) = process_info.child.kill() {
-                errors.push(format!("Failed to kill process '{}': {}", name, e));
-                continue;
-            }
-            
-            // 等待进程退出
-            if let Err(e) = process_info.child.wait() {
-                errors.push(format!("Failed to wait for process '{}' exit: {}", name, e));
-            }
-        }
-        
-        // 清空进程列表
-        processes.clear();
-        
-        // 如果有错误，返回第一个错误
+        let policy = StopPolicy::default();
+        let processes: Vec<(String, Arc<Mutex<ProcessInfo>>)> = self
+            .processes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, process_info)| request_stop(&mut process_info.lock().unwrap(), policy.signal))
+            .map(|(name, process_info)| (name.clone(), Arc::clone(process_info)))
+            .collect();
+
+        let deadline = Instant::now() + Duration::from_millis(policy.timeout_ms);
+        let errors: Vec<String> = processes
+            .iter()
+            .filter_map(|(name, process_info)| {
+                finish_stop(process_info, deadline)
+                    .err()
+                    .map(|e| format!("Failed to stop process '{}': {}", name, e))
+            })
+            .collect();
+
+        // 如果有错误，返回所有错误
         if !errors.is_empty() {
             Err(errors.join("\n"))
         } else {
@@ -214,36 +570,25 @@ impl PythonProcessManager {
         }
     }
 
-    // 获取所有正在运行的进程名称
+    // 获取所有正在运行的进程名称，已推送 exited 事件的进程不在其中
     pub fn list_processes(&self) -> Vec<String> {
         let processes = self.processes.lock().unwrap();
-        processes.keys().cloned().collect()
+        processes
+            .iter()
+            .filter(|(_, info)| !info.lock().unwrap().reported)
+            .map(|(name, _)| name.clone())
+            .collect()
     }
+}
 
-    // 检查进程是否正在运行
-    pub fn is_running(&self, name: &str) -> bool {
-        let processes = self.processes.lock().unwrap();
-        processes.contains_key(name)
-    }
-}
-
-// 使用示例
-fn main() {
-    let manager = PythonProcessManager::new();
-    
-    // 添加并运行 Python 程序
-    manager.add(
-        "script1",
-        "python3",
-        "/path/to/script1.py",
-        &["--arg1", "value1"]
-    ).expect("Failed to start script1");
-    
-    // 读取输出
-    if let Ok(Some(output)) = manager.read("script1") {
-        println!("Output from script1: {}", output);
-    }
-    
-    // 停止进程
-    manager.stop("script1").expect("Failed to stop script1");
-}
\ No newline at end of file
+impl ProcessInfo {
+    // 等待读取线程把剩余输出写完；管道被孙进程继承时不等待，避免卡住
+    fn join_readers(&mut self) {
+        let finished = self.stdout.lock().unwrap().is_closed() && self.stderr.lock().unwrap().is_closed();
+        if finished {
+            for reader in self.readers.drain(..) {
+                let _ = reader.join();
+            }
+        }
+    }
+}
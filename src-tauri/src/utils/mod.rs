@@ -1,3 +1,3 @@
-pub mod fs;
-pub mod cmd;
-pub mod cp;
\ No newline at end of file
+pub mod cp;
+pub mod ring;
+pub mod usage;
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+// 每个输出流默认保留的最大字节数
+pub const DEFAULT_RING_BYTES: usize = 1 << 20;
+
+// 有界环形缓冲区，按行保存子进程输出，每行带递增序号
+#[derive(Debug)]
+pub struct OutputRing {
+    lines: VecDeque<(u64, String)>,
+    next_seq: u64,
+    bytes: usize,
+    max_bytes: usize,
+    closed: bool,
+}
+
+// 一次读取的结果：cursor 之后的文本、下一次读取用的 cursor、被丢弃的行数
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputChunk {
+    pub text: String,
+    pub next: u64,
+    pub dropped: u64,
+    pub closed: bool,
+}
+
+impl OutputRing {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            next_seq: 0,
+            bytes: 0,
+            max_bytes,
+            closed: false,
+        }
+    }
+
+    // 追加一行，超出容量时从头部淘汰旧行
+    pub fn push(&mut self, line: String) {
+        self.bytes += line.len();
+        self.lines.push_back((self.next_seq, line));
+        self.next_seq += 1;
+
+        // 至少保留最新的一行
+        while self.bytes > self.max_bytes && self.lines.len() > 1 {
+            if let Some((_, old)) = self.lines.pop_front() {
+                self.bytes -= old.len();
+            }
+        }
+    }
+
+    // 标记流已结束（读到 EOF）
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    // 读取序号 >= cursor 的所有行
+    pub fn read_since(&self, cursor: u64) -> OutputChunk {
+        let first = self.lines.front().map(|(seq, _)| *seq).unwrap_or(self.next_seq);
+        let dropped = first.saturating_sub(cursor);
+
+        let mut text = String::new();
+        let skip = cursor.saturating_sub(first) as usize;
+        for (_, line) in self.lines.iter().skip(skip) {
+            text.push_str(line);
+        }
+
+        OutputChunk {
+            text,
+            next: self.next_seq.max(cursor),
+            dropped,
+            closed: self.closed,
+        }
+    }
+}
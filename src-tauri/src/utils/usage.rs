@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+// 资源占用的采样间隔
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// 一次采样的结果，覆盖进程及其所有子孙进程
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceSample {
+    // 相对进程启动的时间
+    pub elapsed_ms: u64,
+    // 上次采样以来的 CPU 占用，多核时可超过 100
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    // 本次运行中采样到的最大 rss_bytes
+    pub peak_rss_bytes: u64,
+    pub threads: u64,
+    // 实际读写存储设备的累计字节数
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    // 进程树中的进程数
+    pub processes: usize,
+}
+
+// 一次读取的结果：cursor 之后的采样与下一次读取用的 cursor
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageChunk {
+    pub samples: Vec<ResourceSample>,
+    pub next: usize,
+}
+
+// 进程整个生命周期内的采样记录
+#[derive(Debug, Default)]
+pub struct UsageHistory {
+    samples: Vec<ResourceSample>,
+}
+
+impl UsageHistory {
+    pub fn push(&mut self, sample: ResourceSample) {
+        self.samples.push(sample);
+    }
+
+    pub fn latest(&self) -> Option<&ResourceSample> {
+        self.samples.last()
+    }
+
+    // 读取序号 >= cursor 的采样
+    pub fn read_since(&self, cursor: usize) -> UsageChunk {
+        UsageChunk {
+            samples: self.samples.get(cursor..).unwrap_or(&[]).to_vec(),
+            next: self.samples.len(),
+        }
+    }
+}
+
+// /proc/<pid>/stat 中需要的字段
+#[derive(Debug, Clone, Copy)]
+struct ProcStat {
+    ppid: u32,
+    pgrp: u32,
+    // utime + stime + cutime + cstime，单位为时钟滴答
+    ticks: u64,
+    threads: u64,
+    rss_pages: u64,
+}
+
+// 解析 /proc/<pid>/stat；进程名可能包含空格和括号，从最后一个 ')' 之后开始按空格切分
+fn parse_stat(text: &str) -> Option<ProcStat> {
+    let rest = &text[text.rfind(')')? + 1..];
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    // fields[0] 是第 3 个字段（state）
+    let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+    Some(ProcStat {
+        ppid: field(4)? as u32,
+        pgrp: field(5)? as u32,
+        ticks: field(14)? + field(15)? + field(16)? + field(17)?,
+        threads: field(20)?,
+        rss_pages: field(24)?,
+    })
+}
+
+// 解析 /proc/<pid>/io 中的 read_bytes / write_bytes
+fn parse_io(text: &str) -> (u64, u64) {
+    let mut read = 0;
+    let mut write = 0;
+    for line in text.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().parse().unwrap_or(0);
+            match key {
+                "read_bytes" => read = value,
+                "write_bytes" => write = value,
+                _ => {}
+            }
+        }
+    }
+    (read, write)
+}
+
+// 找出 root 的所有子孙进程：沿 ppid 向下遍历，另外加入同一进程组中被托管给 init 的孤儿进程
+fn process_tree(root: u32, stats: &HashMap<u32, ProcStat>) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (&pid, stat) in stats {
+        children.entry(stat.ppid).or_default().push(pid);
+    }
+
+    let mut tree = HashSet::new();
+    let mut stack = vec![root];
+    stack.extend(stats.iter().filter(|(_, s)| s.pgrp == root).map(|(&pid, _)| pid));
+    while let Some(pid) = stack.pop() {
+        if stats.contains_key(&pid) && tree.insert(pid) {
+            stack.extend(children.get(&pid).into_iter().flatten());
+        }
+    }
+    tree.into_iter().collect()
+}
+
+// 对一个进程树定期采样，计算两次采样间的 CPU 占用
+pub struct TreeSampler {
+    root: u32,
+    started: Instant,
+    clock_ticks: f64,
+    page_size: u64,
+    // 上次采样的时刻与累计 CPU 时间（秒）
+    last: Option<(Instant, f64)>,
+    peak_rss: u64,
+}
+
+impl TreeSampler {
+    pub fn new(root: u32, started: Instant) -> Self {
+        #[cfg(unix)]
+        let (clock_ticks, page_size) = unsafe {
+            (
+                libc::sysconf(libc::_SC_CLK_TCK) as f64,
+                libc::sysconf(libc::_SC_PAGESIZE) as u64,
+            )
+        };
+        #[cfg(not(unix))]
+        let (clock_ticks, page_size) = (100.0, 4096);
+        Self {
+            root,
+            started,
+            clock_ticks,
+            page_size,
+            last: None,
+            peak_rss: 0,
+        }
+    }
+
+    // 读取 /proc 生成一次采样；进程已不存在或没有 /proc 时返回 None
+    pub fn sample(&mut self) -> Option<ResourceSample> {
+        let now = Instant::now();
+        let stats: HashMap<u32, ProcStat> = std::fs::read_dir("/proc")
+            .ok()?
+            .filter_map(|entry| {
+                let pid: u32 = entry.ok()?.file_name().to_str()?.parse().ok()?;
+                let text = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+                Some((pid, parse_stat(&text)?))
+            })
+            .collect();
+        if !stats.contains_key(&self.root) {
+            return None;
+        }
+        let tree = process_tree(self.root, &stats);
+
+        // 已回收子进程的 CPU 时间与 I/O 会计入父进程，因此累计值基本单调
+        let mut ticks = 0;
+        let mut threads = 0;
+        let mut rss_pages = 0;
+        let mut read_bytes = 0;
+        let mut write_bytes = 0;
+        for pid in &tree {
+            let stat = &stats[pid];
+            ticks += stat.ticks;
+            threads += stat.threads;
+            rss_pages += stat.rss_pages;
+            // 无权限读取 io 时按 0 计
+            if let Ok(text) = std::fs::read_to_string(format!("/proc/{}/io", pid)) {
+                let (read, write) = parse_io(&text);
+                read_bytes += read;
+                write_bytes += write;
+            }
+        }
+
+        let cpu = ticks as f64 / self.clock_ticks;
+        let (since, previous) = self.last.unwrap_or((self.started, 0.0));
+        let wall = now.duration_since(since).as_secs_f64();
+        let cpu_percent = if wall > 0.0 {
+            ((cpu - previous) / wall * 100.0).max(0.0)
+        } else {
+            0.0
+        };
+        self.last = Some((now, cpu));
+
+        let rss_bytes = rss_pages * self.page_size;
+        self.peak_rss = self.peak_rss.max(rss_bytes);
+
+        Some(ResourceSample {
+            elapsed_ms: now.duration_since(self.started).as_millis() as u64,
+            cpu_percent,
+            rss_bytes,
+            peak_rss_bytes: self.peak_rss,
+            threads,
+            read_bytes,
+            write_bytes,
+            processes: tree.len(),
+        })
+    }
+}
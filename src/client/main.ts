@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 export const template_data = [
     { "time": 0.0, "state": { "agents": [[7.0, 10.0], [3.0, 10.0], [-1.0, 10.0]], "target": [3.0, 10.0] }, "signals": [{ "distance": {}, "rotations": [[0.0, 0.0], [0.0, 0.0], [0.0, 0.0]] }] },
@@ -12,25 +13,162 @@ export const template_data = [
 
 export type FenceDataType = typeof template_data[0];
 
+export interface RecordError {
+    offset: number;
+    line: number;
+    message: string;
+}
+
 const a = "/Users/apple/repo/drfzh/fencer/tmp/pyfence/大量飞行器围捕匀速目标，但是编队会跟随目标旋转/realtime.json1"
 export async function getTemplateData(path: string | undefined): Promise<FenceDataType[]> {
     if (path === undefined) {
         path = a;
     }
-    const data_ = await invoke("read_file", { path });
-    const data = (data_ as string).split(",\n").map(line => {
-        let a = undefined
-        try {
-            a = JSON.parse(line.trim())
-        }
-        catch (e) {
-            console.error("Error parsing line:", line, e);
-        }
-        return a;
-    }).filter(line => line !== undefined);
+    const parsed = await invoke<{ frames: FenceDataType[], errors: RecordError[] }>("load_trajectory", { path });
+    for (const error of parsed.errors) {
+        console.error(`Skipped record at line ${error.line} (byte ${error.offset}): ${error.message}`);
+    }
+    const data = parsed.frames;
     return data as FenceDataType[];
 }
 
+interface FramesEvent {
+    path: string;
+    first_index: number;
+    frames: FenceDataType[];
+    errors: RecordError[];
+    restarted: boolean;
+}
+
+// Follow a realtime.json1 that is still being written; returns a function that stops watching
+export async function watchTrajectory(path: string, onFrames: (event: FramesEvent) => void): Promise<() => Promise<void>> {
+    const unlisten = await listen<FramesEvent>("trajectory://frames", (event) => {
+        if (event.payload.path === path) {
+            onFrames(event.payload);
+        }
+    });
+    await invoke("watch_trajectory", { path });
+    return async () => {
+        unlisten();
+        await invoke("unwatch_trajectory", { path });
+    };
+}
+
+interface OutputChunk {
+    text: string;
+    next: number;
+    dropped: number;
+    closed: boolean;
+}
+
+interface OutputEvent extends OutputChunk {
+    name: string;
+    stream: "stdout" | "stderr";
+}
+
+interface ResourceSample {
+    elapsed_ms: number;
+    cpu_percent: number;
+    rss_bytes: number;
+    peak_rss_bytes: number;
+    threads: number;
+    read_bytes: number;
+    write_bytes: number;
+    processes: number;
+}
+
+interface UsageEvent extends ResourceSample {
+    name: string;
+}
+
+interface UsageChunk {
+    samples: ResourceSample[];
+    next: number;
+}
+
+interface ExitedEvent {
+    name: string;
+    code: number | null;
+    signal: number | null;
+    reason: "exited" | "stopped" | "killed" | "timed_out" | "limit_exceeded";
+    limit: "cpu" | "memory" | "output" | null;
+    duration_ms: number;
+}
+
+interface RunPolicy {
+    timeout_ms?: number;
+    cpu_seconds?: number;
+    memory_bytes?: number;
+    max_output_bytes?: number;
+}
+
+interface JobRequest {
+    profile: string;
+    args?: string[];
+    working_dir?: string;
+    priority?: number;
+    policy?: RunPolicy;
+}
+
+interface JobInfo {
+    id: number;
+    spec: { profile: string; program: string; args: string[]; working_dir: string | null; priority: number };
+    state: "queued" | "running" | "succeeded" | "failed" | "cancelled";
+    submitted_ms: number;
+    started_ms: number | null;
+    finished_ms: number | null;
+    pid: number | null;
+    exit_code: number | null;
+    signal: number | null;
+    timed_out: boolean;
+    limit: "cpu" | "memory" | "output" | null;
+    error: string | null;
+    stdout_path: string | null;
+    stderr_path: string | null;
+}
+
+type AxisValues =
+    | { values: unknown[] }
+    | { start: number; stop: number; step?: number; count?: number; log?: boolean };
+
+type Axis = { parameter: string } & AxisValues;
+
+type Sampling =
+    | { mode: "grid" }
+    | { mode: "random" | "latin_hypercube"; samples: number; seed?: number };
+
+interface SweepDefinition {
+    name: string;
+    base_profile: string;
+    axes: Axis[];
+    sampling?: Sampling;
+    output_dir: string;
+    // Allow a non-empty output_dir, replacing an earlier sweep's profiles
+    overwrite?: boolean;
+    args?: string[];
+    priority?: number;
+    policy?: RunPolicy;
+}
+
+interface SweepStatus {
+    id: number;
+    name: string;
+    output_dir: string;
+    counts: Record<JobInfo["state"] | "unknown", number>;
+    finished: boolean;
+    points: {
+        index: number;
+        parameters: Record<string, unknown>;
+        profile_path: string;
+        output_dir: string;
+        job: number;
+        state: JobInfo["state"] | null;
+        exit_code: number | null;
+        stdout_path: string | null;
+        stderr_path: string | null;
+    }[];
+}
+
 export class SimulationManager {
     public static setLog(log: string) {
         console.log("Simulation log:", log);
@@ -44,21 +182,28 @@ export class SimulationManager {
     }
 
     private constructor() {
-        setInterval(async () => {
-            for (const name in this.simulationProcess) {
-                const exited = await invoke("mas_exited", { name })
-                if (exited === "true") {
-                    continue
-                }
-
-                const result = await invoke("read_stdout", { name });
-                if (typeof result !== "string") {
-                    this.setLog("Error reading stdout for " + name + ": Expected string, got " + typeof result);
-                    continue;
-                }
-                this.simulationProcess[name] += result;
+        listen<OutputEvent>("process://stdout", (event) => {
+            const { name, text } = event.payload;
+            if (name in this.simulationProcess) {
+                this.simulationProcess[name] += text;
             }
-        }, 1000);
+        });
+        listen<OutputEvent>("process://stderr", (event) => {
+            this.setLog(event.payload.text);
+        });
+        listen<UsageEvent>("process://usage", (event) => {
+            const { name, ...sample } = event.payload;
+            (this.usage[name] ??= []).push(sample);
+        });
+        listen<JobInfo>("job://changed", (event) => {
+            this.jobs[event.payload.id] = event.payload;
+        });
+        listen<ExitedEvent>("process://exited", (event) => {
+            const { name, code, signal, reason, limit, duration_ms } = event.payload;
+            this.exited[name] = event.payload;
+            const cause = limit ? ` (${limit} limit)` : "";
+            this.setLog(`Process ${name} ${reason}${cause} (code ${code}, signal ${signal}) after ${duration_ms} ms\n`);
+        });
     }
 
 
@@ -70,19 +215,48 @@ export class SimulationManager {
     }
 
     public simulationProcess: Record<string, string> = {};
+    public exited: Record<string, ExitedEvent> = {};
+    public usage: Record<string, ResourceSample[]> = {};
+    public jobs: Record<number, JobInfo> = {};
     public async simulate(name: string, path: string) {
-        const result = await invoke("exec_mas", { name, args: "simulate " + path });
+        const result = await invoke("exec_mas", { name, args: ["simulate", path] });
         this.setLog("Simulation started: " + name + " with path: " + path);
         this.simulationProcess[name] = "";
     }
 
     public async read_stdout(name: string) {
-        const result = await invoke("read_stdout", { name });
-        if (typeof result !== "string") {
-            throw undefined;
-        }
-        this.setLog("Read stdout: " + result);
-        return result;
+        const chunk = await invoke<OutputChunk>("read_stdout", { name });
+        this.setLog("Read stdout: " + chunk.text);
+        return chunk.text;
+    }
+
+    // Queue a simulation on the job scheduler; `jobs` follows its state
+    public async submitJob(request: JobRequest) {
+        const id = await invoke<number>("submit_job", { request });
+        this.setLog(`Job ${id} queued: ${request.profile}\n`);
+        return id;
+    }
+
+    public async cancelJob(id: number) {
+        await invoke("cancel_job", { id });
+    }
+
+    // Expand a sweep over a JSON profile and queue one run per point
+    public async startSweep(definition: SweepDefinition) {
+        const id = await invoke<number>("start_sweep", { definition });
+        this.setLog(`Sweep ${id} started: ${definition.name}\n`);
+        return id;
+    }
+
+    public async sweepStatus(id: number) {
+        return invoke<SweepStatus>("sweep_status", { id });
+    }
+
+    // Full resource history of a run, e.g. after the window missed some events
+    public async read_usage(name: string) {
+        const chunk = await invoke<UsageChunk>("mas_usage", { name });
+        this.usage[name] = chunk.samples;
+        return chunk.samples;
     }
 
     public async read_file(path: string) {
@@ -99,17 +273,11 @@ export class SimulationManager {
 
     public async updateProfiles() {
         const name = "update_profiles" + Math.random().toString(36).substring(2, 15);
-        await invoke("exec_mas", { name, args: "simulate list" });
+        await invoke("exec_mas", { name, args: ["simulate", "list"] });
         const outputPromise = new Promise<string>((resolve, reject) => {
             setTimeout(() => {
-                invoke("read_stdout", { name })
-                    .then((result) => {
-                        if (typeof result !== "string") {
-                            reject(new Error("Expected string result from command, got: " + typeof result));
-                        } else {
-                            resolve(result);
-                        }
-                    })
+                invoke<OutputChunk>("read_stdout", { name })
+                    .then((chunk) => resolve(chunk.text))
                     .catch(reject);
             }, 10000);
         });